@@ -0,0 +1,126 @@
+//! Encode and decode throughput of the high-speed frames and register conversions, plus a
+//! full position command round trip against an in-memory motor.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use embedded_registers::Register;
+use futures::executor::block_on;
+use orca_rs::OrcaMotor;
+use orca_rs::pdu_payload::*;
+use orca_rs::persist::SaveGroups;
+use orca_rs::register_map::{CtrlReg3, OrcaModeOfOperation};
+
+fn position_command() -> OrcaHighSpeedRequestPDU {
+    OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::PositionControlStream {
+        position_um: 12_345,
+    })
+}
+
+fn telemetry() -> OrcaHighSpeedResponsePDU {
+    OrcaHighSpeedResponsePDU::Command(MotorCommandResponsePDUPayload {
+        position_um: 12_340,
+        force_mn: -250,
+        power_w: 4,
+        temperature_c: 31,
+        voltage_mv: 24_000,
+        error: OrcaErrors::default(),
+    })
+}
+
+/// Motor that answers every request with the same telemetry frame, as fast as it is read.
+struct LoopbackMotor {
+    response: [u8; MAX_HIGH_SPEED_ADU_LEN],
+    response_len: usize,
+    pending: usize,
+}
+
+impl LoopbackMotor {
+    fn new() -> Self {
+        let mut response = [0; MAX_HIGH_SPEED_ADU_LEN];
+        let response_len = OrcaHighSpeedResponseADU::new(1, telemetry())
+            .encode_into(&mut response)
+            .unwrap();
+        Self {
+            response,
+            response_len,
+            pending: 0,
+        }
+    }
+}
+
+impl embedded_io_async::ErrorType for LoopbackMotor {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_io_async::Write for LoopbackMotor {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.pending = self.response_len;
+        Ok(buf.len())
+    }
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io_async::Read for LoopbackMotor {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let start = self.response_len - self.pending;
+        let n = buf.len().min(self.pending);
+        buf[..n].copy_from_slice(&self.response[start..start + n]);
+        self.pending -= n;
+        Ok(n)
+    }
+}
+
+fn request(c: &mut Criterion) {
+    let mut group = c.benchmark_group("request");
+    let adu = OrcaHighSpeedRequestADU::new(1, position_command());
+    group.bench_function("adu_new", |b| {
+        b.iter(|| OrcaHighSpeedRequestADU::new(black_box(1), black_box(position_command())))
+    });
+    group.bench_function("adu_to_vec", |b| b.iter(|| black_box(&adu).to_vec()));
+    group.bench_function("adu_to_frame", |b| b.iter(|| black_box(&adu).to_frame()));
+    group.bench_function("pdu_to_frame", |b| {
+        b.iter(|| black_box(position_command()).to_frame(black_box(1)))
+    });
+    group.finish();
+}
+
+fn response(c: &mut Criterion) {
+    let mut frame = [0; MAX_HIGH_SPEED_ADU_LEN];
+    let len = OrcaHighSpeedResponseADU::new(1, telemetry())
+        .encode_into(&mut frame)
+        .unwrap();
+    c.bench_function("response/adu_from_bytes", |b| {
+        b.iter(|| OrcaHighSpeedResponseADU::from_bytes(black_box(&frame[..len])).unwrap())
+    });
+}
+
+fn registers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("registers");
+    group.bench_function("errors_round_trip", |b| {
+        b.iter(|| u16::from(OrcaErrors::from(black_box(0x0A40u16))))
+    });
+    group.bench_function("save_groups_round_trip", |b| {
+        b.iter(|| u16::from(SaveGroups::from(black_box(0x1F0u16))))
+    });
+    group.bench_function("ctrl_reg3_mode", |b| {
+        b.iter(|| {
+            let mut reg = CtrlReg3::default();
+            reg.write_mode(black_box(OrcaModeOfOperation::PositionMode));
+            (reg.data()[0], reg.read_mode())
+        })
+    });
+    group.finish();
+}
+
+fn round_trip(c: &mut Criterion) {
+    let mut motor = OrcaMotor::new(LoopbackMotor::new());
+    c.bench_function("round_trip/send_position_high_speed", |b| {
+        b.iter(|| block_on(motor.send_position_high_speed(black_box(12_345))).unwrap())
+    });
+}
+
+criterion_group!(benches, request, response, registers, round_trip);
+criterion_main!(benches);
@@ -1,6 +1,16 @@
 use futures::future::try_join_all;
 use orca_rs::OrcaMotor;
 use orca_rs::pdu_payload::OrcaHighSpeedResponsePDU;
+use orca_rs::stats::TransactionKind;
+
+/// Microseconds since the first call, for latency statistics.
+fn clock() -> u64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START
+        .get_or_init(std::time::Instant::now)
+        .elapsed()
+        .as_micros() as u64
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -23,7 +33,7 @@ async fn main() -> anyhow::Result<()> {
             .timeout(std::time::Duration::from_millis(1));
         let port = tokio_serial::SerialStream::open(&builder)?;
         let port = embedded_io_adapters::tokio_1::FromTokio::new(port);
-        Ok(OrcaMotor::new(port))
+        Ok(OrcaMotor::new(port).with_clock(clock))
     })
     .collect::<Result<_, anyhow::Error>>()?;
 
@@ -90,6 +100,24 @@ async fn main() -> anyhow::Result<()> {
         mm_per_sec
     );
 
+    // Round-trip latency of the position commands, per motor
+    for (i, motor) in motors.iter().enumerate() {
+        let stats = motor.stats().get(TransactionKind::HighSpeedCommand);
+        let latency = &stats.latency;
+        println!(
+            "[M{}] {} cmds, {} errors, latency min/mean/p99/max {}/{}/{}/{} µs, {} B sent, {} B received",
+            i + 1,
+            stats.count,
+            stats.errors.total(),
+            latency.min_us().unwrap_or_default(),
+            latency.mean_us().unwrap_or_default(),
+            latency.percentile_us(99.0).unwrap_or_default(),
+            latency.max_us().unwrap_or_default(),
+            stats.bytes_sent,
+            stats.bytes_received
+        );
+    }
+
     // Disable high-speed and restore sleep mode
     try_join_all(motors.iter_mut().map(|m| m.disable_high_speed())).await?;
     try_join_all(
@@ -0,0 +1,99 @@
+//! Serves Modbus TCP on behalf of ORCA motors on serial ports.
+//!
+//! ```text
+//! orca-gateway [--listen ADDR] [--baud BAUD] [--timeout-ms MS] UNIT=TTY[:SLAVE]...
+//! ```
+//!
+//! Each `UNIT=TTY` maps a Modbus TCP unit id to the motor on `TTY`, addressed as `SLAVE`
+//! (1 unless given). Each `TTY` is opened once and shared by every slave on it. A slave that
+//! has not answered after `MS` (500 unless given) is reported to the client as failed.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
+use orca_rs::OrcaMotor;
+use orca_rs::gateway::Gateway;
+
+const USAGE: &str =
+    "usage: orca-gateway [--listen ADDR] [--baud BAUD] [--timeout-ms MS] UNIT=TTY[:SLAVE]...";
+
+/// `DelayNs` on the tokio timer.
+struct TokioDelay;
+
+impl embedded_hal_async::delay::DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await
+    }
+}
+
+struct MotorArg {
+    unit_id: u8,
+    tty: String,
+    slave: u8,
+}
+
+fn parse_motor(arg: &str) -> Option<MotorArg> {
+    let (unit_id, port) = arg.split_once('=')?;
+    let (tty, slave) = match port.rsplit_once(':') {
+        Some((tty, slave)) => (tty, slave.parse().ok()?),
+        None => (port, 1),
+    };
+    Some(MotorArg {
+        unit_id: unit_id.parse().ok()?,
+        tty: tty.to_string(),
+        slave,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut listen = String::from("0.0.0.0:502");
+    let mut baud = 19200;
+    let mut timeout_ms = 500;
+    let mut motors = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().ok_or(USAGE)?,
+            "--baud" => baud = args.next().ok_or(USAGE)?.parse()?,
+            "--timeout-ms" => timeout_ms = args.next().ok_or(USAGE)?.parse()?,
+            _ => motors.push(parse_motor(&arg).ok_or(USAGE)?),
+        }
+    }
+    if motors.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    let mut gateway = Gateway::<CriticalSectionRawMutex, _, _>::new();
+    let mut buses = BTreeMap::new();
+    for motor in motors {
+        let bus = match buses.get(&motor.tty) {
+            Some(bus) => Arc::clone(bus),
+            None => {
+                let builder = tokio_serial::new(&motor.tty, baud)
+                    .parity(tokio_serial::Parity::Even)
+                    .timeout(Duration::from_millis(1));
+                let port = tokio_serial::SerialStream::open(&builder)?;
+                let port = embedded_io_adapters::tokio_1::FromTokio::new(port);
+                let orca = OrcaMotor::new_with_slave(port, motor.slave)
+                    .with_response_timeout(TokioDelay, timeout_ms);
+                let bus = Arc::new(Mutex::new(orca));
+                buses.insert(motor.tty.clone(), Arc::clone(&bus));
+                bus
+            }
+        };
+        gateway = gateway.with_slave(motor.unit_id, bus, motor.slave);
+        println!(
+            "Unit {} -> {} (slave {})",
+            motor.unit_id, motor.tty, motor.slave
+        );
+    }
+
+    let listener = tokio::net::TcpListener::bind(&listen).await?;
+    println!("Serving Modbus TCP on {}", listener.local_addr()?);
+    gateway.serve(listener).await?;
+    Ok(())
+}
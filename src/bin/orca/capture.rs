@@ -0,0 +1,118 @@
+//! `orca decode`: splits a bus capture into frames and prints each one decoded.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use orca_rs::decode::{Direction, decode_frame, split_burst, split_capture};
+
+use crate::{CliResult, print};
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum CaptureFormat {
+    /// Hex bytes, one burst per line, optionally after a timestamp in seconds and a colon.
+    Hex,
+    /// Logic-analyser export: a time in seconds and a byte value per row.
+    Csv,
+    /// Records of a little-endian u64 timestamp in microseconds followed by the byte.
+    Bin,
+    /// Plain bytes without timestamps.
+    Raw,
+}
+
+/// Modbus RTU inter-frame gap: 3.5 characters of 11 bits, fixed above 19200 baud.
+pub fn frame_gap_us(baud: u32) -> u64 {
+    if baud > 19200 {
+        1750
+    } else {
+        38_500_000 / u64::from(baud)
+    }
+}
+
+fn seconds_to_us(seconds: &str) -> CliResult<u64> {
+    Ok((seconds.trim().parse::<f64>()? * 1e6).round() as u64)
+}
+
+fn parse_hex_line(line: &str) -> CliResult<Vec<(u64, u8)>> {
+    let (timestamp_us, bytes) = match line.split_once(':') {
+        Some((timestamp, bytes)) => (seconds_to_us(timestamp)?, bytes),
+        None => (0, line),
+    };
+    bytes
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let token = token.trim_start_matches("0x");
+            Ok((timestamp_us, u8::from_str_radix(token, 16)?))
+        })
+        .collect()
+}
+
+fn parse_csv(text: &str) -> CliResult<Vec<(u64, u8)>> {
+    let mut bytes = Vec::new();
+    for row in text.lines() {
+        let mut fields = row.split(',').map(|f| f.trim().trim_matches('"'));
+        let (Some(time), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        // header rows
+        let Ok(timestamp_us) = seconds_to_us(time) else {
+            continue;
+        };
+        let byte = match value.strip_prefix("0x") {
+            Some(hex) => u8::from_str_radix(hex, 16)?,
+            None => value.parse()?,
+        };
+        bytes.push((timestamp_us, byte));
+    }
+    Ok(bytes)
+}
+
+fn parse_bin(data: &[u8]) -> CliResult<Vec<(u64, u8)>> {
+    if !data.len().is_multiple_of(9) {
+        return Err("Binary capture is not a whole number of 9-byte records".into());
+    }
+    Ok(data
+        .chunks_exact(9)
+        .map(|r| (u64::from_le_bytes(r[..8].try_into().unwrap()), r[8]))
+        .collect())
+}
+
+pub fn run(file: &Path, format: CaptureFormat, gap_us: u64) -> CliResult<()> {
+    let frames = match format {
+        CaptureFormat::Hex => {
+            let mut frames = Vec::new();
+            for line in std::fs::read_to_string(file)?.lines() {
+                frames.extend(split_burst(&parse_hex_line(line)?));
+            }
+            frames
+        }
+        CaptureFormat::Csv => split_capture(&parse_csv(&std::fs::read_to_string(file)?)?, gap_us),
+        CaptureFormat::Bin => split_capture(&parse_bin(&std::fs::read(file)?)?, gap_us),
+        CaptureFormat::Raw => {
+            let bytes: Vec<_> = std::fs::read(file)?.into_iter().map(|b| (0, b)).collect();
+            split_burst(&bytes)
+        }
+    };
+
+    // requests and responses alternate, which settles frames that decode both ways
+    let mut expect = Direction::Request;
+    for frame in frames {
+        let decoded = if frame.crc_ok {
+            decode_frame(&frame.bytes, expect).ok()
+        } else {
+            None
+        };
+        if let Some(decoded) = &decoded {
+            expect = decoded.direction.other();
+        }
+        let hex: Vec<_> = frame.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        print(&serde_json::json!({
+            "timestamp_us": frame.timestamp_us,
+            "bytes": hex.join(" "),
+            "crc_ok": frame.crc_ok,
+            "summary": decoded.as_ref().map(|d| d.to_string()),
+            "frame": decoded,
+        }))?;
+    }
+    Ok(())
+}
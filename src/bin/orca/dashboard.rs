@@ -0,0 +1,282 @@
+//! Live terminal dashboard of the telemetry motors return to high-speed frames.
+//!
+//! Each motor is polled with high-speed reads of `ModeOfOperation`, as `keep_alive` does, so
+//! the dashboard watches without touching setpoints. Motors on the same port share one
+//! [`Bus`] and are addressed in turn.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use embedded_registers::Register;
+use orca_rs::Error;
+use orca_rs::pdu_payload::{
+    ManageHighSpeedRequestPDUPayload, ManageHighSpeedRequestSubFunctionCode,
+    MotorCommandResponsePDUPayload, MotorReadRequestPDUPayload, OrcaHighSpeedRequestADU,
+    OrcaHighSpeedRequestPDU, OrcaHighSpeedResponsePDU,
+};
+use orca_rs::register_map::{ModeOfOperation, OrcaModeOfOperation};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::{CliResult, Motor};
+
+/// Samples kept for the sparklines.
+const HISTORY: usize = 200;
+/// Time between redraws.
+const FRAME: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+struct CommsStats {
+    responses: u64,
+    crc_errors: u64,
+    timeouts: u64,
+    other_errors: u64,
+    /// Arrival times of recent responses, at most a second old when the last one arrived.
+    recent: VecDeque<Instant>,
+}
+
+impl CommsStats {
+    fn record_response(&mut self, now: Instant) {
+        self.responses += 1;
+        self.recent.push_back(now);
+        while self
+            .recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > Duration::from_secs(1))
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Responses received in the second before `now`.
+    fn rate_hz(&self, now: Instant) -> usize {
+        self.recent
+            .iter()
+            .filter(|t| now.duration_since(**t) <= Duration::from_secs(1))
+            .count()
+    }
+}
+
+/// One serial port and the slaves to show on it.
+pub struct Bus {
+    pub port: String,
+    pub motor: Motor,
+    pub slaves: Vec<u8>,
+}
+
+impl Bus {
+    pub fn new(port: &str, motor: Motor, slave: u8) -> Self {
+        Self {
+            port: port.to_string(),
+            motor,
+            slaves: vec![slave],
+        }
+    }
+}
+
+/// Switches high-speed streaming for `slave` on `motor`'s port.
+async fn manage_high_speed(
+    motor: &mut Motor,
+    slave: u8,
+    sub_function_code: ManageHighSpeedRequestSubFunctionCode,
+    baud_rate: u32,
+) -> orca_rs::Result<OrcaHighSpeedResponsePDU> {
+    let manage = ManageHighSpeedRequestPDUPayload {
+        sub_function_code,
+        baud_rate,
+        delay_us: 0,
+    };
+    let adu = OrcaHighSpeedRequestADU::new(slave, OrcaHighSpeedRequestPDU::Manage(manage));
+    motor.send_high_speed_adu(&adu).await
+}
+
+struct Panel {
+    label: String,
+    /// Index of the panel's bus.
+    bus: usize,
+    slave: u8,
+    latest: Option<(OrcaModeOfOperation, MotorCommandResponsePDUPayload)>,
+    positions_um: VecDeque<i32>,
+    forces_mn: VecDeque<i32>,
+    stats: CommsStats,
+}
+
+impl Panel {
+    async fn poll(&mut self, motor: &mut Motor) {
+        let read = MotorReadRequestPDUPayload {
+            register_address: ModeOfOperation::ADDRESS as u16,
+            register_width: 1,
+        };
+        let adu = OrcaHighSpeedRequestADU::new(self.slave, OrcaHighSpeedRequestPDU::Read(read));
+        match motor.send_high_speed_adu(&adu).await {
+            Ok(OrcaHighSpeedResponsePDU::Read(payload)) => {
+                self.stats.record_response(Instant::now());
+                let telemetry = payload.command_response;
+                push_sample(&mut self.positions_um, telemetry.position_um);
+                push_sample(&mut self.forces_mn, telemetry.force_mn);
+                self.latest = Some((payload.mode_of_operation, telemetry));
+            }
+            Err(Error::Crc) => self.stats.crc_errors += 1,
+            Err(Error::Timeout { .. }) => self.stats.timeouts += 1,
+            _ => self.stats.other_errors += 1,
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, area: Rect) {
+        let title = match &self.latest {
+            Some((mode, _)) => format!(" {} - {:?} ", self.label, mode),
+            None => format!(" {} - no response yet ", self.label),
+        };
+        let block = Block::bordered().title(title);
+        let [text_area, plots_area] =
+            Layout::horizontal([Constraint::Length(34), Constraint::Min(0)])
+                .areas(block.inner(area));
+        frame.render_widget(block, area);
+
+        let mut lines = Vec::new();
+        if let Some((_, t)) = &self.latest {
+            lines.push(Line::from(format!("Position    {:>10} um", t.position_um)));
+            lines.push(Line::from(format!("Force       {:>10} mN", t.force_mn)));
+            lines.push(Line::from(format!("Power       {:>10} W", t.power_w)));
+            lines.push(Line::from(format!("Temperature {:>10} C", t.temperature_c)));
+            lines.push(Line::from(format!("Voltage     {:>10} mV", t.voltage_mv)));
+            let errors: Vec<_> = t.error.active().map(|flag| flag.name()).collect();
+            lines.push(if errors.is_empty() {
+                Line::from("Errors      none")
+            } else {
+                Line::styled(
+                    format!("Errors      {}", errors.join(", ")),
+                    Style::new().fg(Color::Red),
+                )
+            });
+        }
+        lines.push(Line::from(format!(
+            "Rate        {:>10} Hz",
+            self.stats.rate_hz(Instant::now())
+        )));
+        lines.push(Line::from(format!(
+            "Responses   {:>10}",
+            self.stats.responses
+        )));
+        lines.push(Line::from(format!(
+            "CRC errors  {:>10}",
+            self.stats.crc_errors
+        )));
+        lines.push(Line::from(format!(
+            "Timeouts    {:>10}",
+            self.stats.timeouts
+        )));
+        lines.push(Line::from(format!(
+            "Other errors{:>10}",
+            self.stats.other_errors
+        )));
+        frame.render_widget(Paragraph::new(lines), text_area);
+
+        let [position_area, force_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(plots_area);
+        frame.render_widget(sparkline("Position", &self.positions_um), position_area);
+        frame.render_widget(sparkline("Force", &self.forces_mn), force_area);
+    }
+}
+
+fn push_sample(history: &mut VecDeque<i32>, sample: i32) {
+    if history.len() == HISTORY {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+/// Sparkline of `history`, shifted so its minimum sits on the baseline.
+fn sparkline<'a>(title: &'a str, history: &VecDeque<i32>) -> Sparkline<'a> {
+    let min = history.iter().copied().min().unwrap_or(0);
+    let data: Vec<u64> = history
+        .iter()
+        .map(|sample| (i64::from(*sample) - i64::from(min)) as u64)
+        .collect();
+    Sparkline::default()
+        .block(Block::new().title(title))
+        .data(data)
+        .style(Style::new().fg(Color::Cyan))
+}
+
+/// Streams every motor into the dashboard until `q` or Esc is pressed.
+pub async fn run(mut buses: Vec<Bus>, baud: u32) -> CliResult<()> {
+    let mut panels = Vec::new();
+    let mut result = enable_streams(&mut buses, baud, &mut panels).await;
+    if result.is_ok() {
+        result = match ratatui::try_init() {
+            Ok(mut terminal) => show(&mut terminal, &mut buses, &mut panels).await,
+            Err(e) => Err(e.into()),
+        };
+        // also undoes a terminal setup that failed partway
+        ratatui::restore();
+    }
+
+    // every panel's stream was enabled, whether or not the dashboard got going
+    for panel in &panels {
+        let disable = ManageHighSpeedRequestSubFunctionCode::Disable;
+        // best effort: a motor that stopped answering keeps its stream enabled
+        let _ = manage_high_speed(&mut buses[panel.bus].motor, panel.slave, disable, 0).await;
+    }
+    result
+}
+
+/// Enables high-speed streaming for every slave, adding a panel for each one enabled. Stops
+/// at the first slave that fails, leaving the panels of those already enabled.
+async fn enable_streams(buses: &mut [Bus], baud: u32, panels: &mut Vec<Panel>) -> CliResult<()> {
+    for (index, bus) in buses.iter_mut().enumerate() {
+        for &slave in &bus.slaves {
+            let enable = ManageHighSpeedRequestSubFunctionCode::Enable;
+            manage_high_speed(&mut bus.motor, slave, enable, baud).await?;
+            panels.push(Panel {
+                label: format!("{}:{}", bus.port, slave),
+                bus: index,
+                slave,
+                latest: None,
+                positions_um: VecDeque::with_capacity(HISTORY),
+                forces_mn: VecDeque::with_capacity(HISTORY),
+                stats: CommsStats::default(),
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn show(
+    terminal: &mut DefaultTerminal,
+    buses: &mut [Bus],
+    panels: &mut [Panel],
+) -> CliResult<()> {
+    let mut last_draw = Instant::now() - FRAME;
+    loop {
+        for panel in panels.iter_mut() {
+            panel.poll(&mut buses[panel.bus].motor).await;
+        }
+        if last_draw.elapsed() >= FRAME {
+            terminal.draw(|frame| {
+                let constraints = panels.iter().map(|_| Constraint::Fill(1));
+                let [main, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                    .areas(frame.area());
+                let areas = Layout::vertical(constraints).split(main);
+                panels
+                    .iter()
+                    .zip(areas.iter())
+                    .for_each(|(panel, area)| panel.draw(frame, *area));
+                frame.render_widget(Line::from("q: quit"), footer);
+            })?;
+            last_draw = Instant::now();
+        }
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
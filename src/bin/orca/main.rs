@@ -0,0 +1,367 @@
+//! Everyday operations on an ORCA motor from the command line. Results are printed as JSON.
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use capture::CaptureFormat;
+use clap::{Parser, Subcommand, ValueEnum};
+use embedded_io_adapters::tokio_1::FromTokio;
+use embedded_registers::Register;
+use orca_rs::OrcaMotor;
+use orca_rs::pdu_payload::OrcaErrors;
+use orca_rs::persist::SaveGroups;
+use orca_rs::register_map::*;
+use orca_rs::safety::OrcaSafetyLimits;
+use orca_rs::tuning::{CurrentGains, PositionGains};
+use serde::{Deserialize, Serialize};
+use tokio_serial::SerialStream;
+
+mod capture;
+#[cfg(feature = "tui")]
+mod dashboard;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+type Motor = OrcaMotor<FromTokio<SerialStream>, TokioDelay>;
+
+#[derive(Parser)]
+#[command(name = "orca", about = "Everyday operations on an ORCA motor")]
+struct Cli {
+    /// Serial port the motor is on. Needed by every command but `decode`.
+    #[arg(short, long)]
+    port: Option<String>,
+    #[arg(short, long, default_value_t = 19200)]
+    baud: u32,
+    /// Modbus slave address of the motor.
+    #[arg(short, long, default_value_t = 1)]
+    slave: u8,
+    /// How long to wait for each response.
+    #[arg(long, default_value_t = 500)]
+    timeout_ms: u32,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the slave addresses that answer on the port.
+    Scan {
+        #[arg(long, default_value_t = 1)]
+        first: u8,
+        #[arg(long, default_value_t = 247)]
+        last: u8,
+    },
+    /// Mode, errors and telemetry.
+    Info,
+    /// Read or switch the mode of operation.
+    Mode {
+        #[command(subcommand)]
+        action: ModeAction,
+    },
+    /// Raw holding registers, addressed by name or number.
+    Reg {
+        #[command(subcommand)]
+        action: RegAction,
+    },
+    /// Gains and safety limits.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Make the current shaft position the new zero.
+    Zero,
+    /// Clear the motor's errors and list those still active.
+    ClearErrors,
+    /// Hold a high-speed setpoint for a while, printing every response.
+    Stream {
+        target: StreamTarget,
+        /// Micrometers or millinewtons.
+        value: i32,
+        #[arg(long, default_value_t = 1000)]
+        duration_ms: u64,
+        #[arg(long, default_value_t = 10)]
+        period_ms: u64,
+    },
+    /// Print info at a fixed interval until interrupted.
+    Monitor {
+        #[arg(long, default_value_t = 100)]
+        interval_ms: u64,
+    },
+    /// Split a bus capture into frames and decode them.
+    Decode {
+        file: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = CaptureFormat::Hex)]
+        format: CaptureFormat,
+        /// Silence that separates frames. 3.5 characters at `--baud` by default.
+        #[arg(long)]
+        gap_us: Option<u64>,
+    },
+    /// Live telemetry of this motor and any others, until `q` is pressed.
+    #[cfg(feature = "tui")]
+    Dashboard {
+        /// More motors to show, as `PORT[:SLAVE]`. Motors on the same port share it.
+        others: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ModeAction {
+    Get,
+    /// Switch to a mode such as `SleepMode` or `PositionMode`.
+    Set {
+        mode: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum RegAction {
+    Read {
+        register: String,
+        #[arg(short = 'n', long, default_value_t = 1)]
+        count: u16,
+    },
+    Write {
+        register: String,
+        #[arg(required = true)]
+        values: Vec<u16>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    Dump,
+    Load {
+        file: PathBuf,
+        /// Also save the configuration to flash.
+        #[arg(long)]
+        save: bool,
+    },
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum StreamTarget {
+    Position,
+    Force,
+}
+
+#[derive(Serialize)]
+struct Info {
+    mode: OrcaModeOfOperation,
+    errors: OrcaErrors,
+    position_um: i32,
+    speed_mmps: i32,
+    accel_mmpss: i32,
+    force_mn: i32,
+    power_w: u16,
+    avg_power_w: u16,
+    board_temp_c: u16,
+    coil_temp_c: u16,
+    vdd_final_mv: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    current_gains: CurrentGains,
+    position_gains: PositionGains,
+    safety_limits: OrcaSafetyLimits,
+}
+
+#[derive(Serialize)]
+struct RegisterValue {
+    address: u16,
+    value: u16,
+}
+
+/// `DelayNs` on the tokio timer.
+struct TokioDelay;
+
+impl embedded_hal_async::delay::DelayNs for TokioDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        tokio::time::sleep(Duration::from_nanos(ns.into())).await
+    }
+}
+
+fn print(value: &impl Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+/// Register address from a number or a name in `REGISTERS`.
+fn parse_register(register: &str) -> CliResult<u16> {
+    register
+        .parse()
+        .ok()
+        .or_else(|| register_address(register))
+        .ok_or_else(|| format!("Unknown register {}", register).into())
+}
+
+async fn read_info(motor: &mut Motor) -> CliResult<Info> {
+    let mode = motor.read_mode().await?;
+    let errors = motor.read_errors().await?;
+    // BoardTemp through CoilTemp in one read
+    let r: [u16; 21] = motor.read_holdings(BoardTemp::ADDRESS as u16).await?;
+    let at = |register: u64| usize::from(register as u16 - BoardTemp::ADDRESS as u16);
+    let i32_at =
+        |register: u64| ((r[at(register) + 1] as u32) << 16 | r[at(register)] as u32) as i32;
+    Ok(Info {
+        mode,
+        errors,
+        position_um: i32_at(ShaftPosUmL::ADDRESS),
+        speed_mmps: i32_at(ShaftSpeedMmpsL::ADDRESS),
+        accel_mmpss: i32_at(ShaftAccelMmpssL::ADDRESS),
+        force_mn: i32_at(ForceL::ADDRESS),
+        power_w: r[at(Power::ADDRESS)],
+        avg_power_w: r[at(AvgPower::ADDRESS)],
+        board_temp_c: r[at(BoardTemp::ADDRESS)],
+        coil_temp_c: r[at(CoilTemp::ADDRESS)],
+        vdd_final_mv: r[at(VddFinal::ADDRESS)],
+    })
+}
+
+/// Opens `port` for the motor at `slave`, bounding each response by `timeout_ms`.
+fn open(port: &str, baud: u32, slave: u8, timeout_ms: u32) -> CliResult<Motor> {
+    let builder = tokio_serial::new(port, baud)
+        .parity(tokio_serial::Parity::Even)
+        .timeout(Duration::from_millis(1));
+    let port = FromTokio::new(SerialStream::open(&builder)?);
+    Ok(OrcaMotor::new_with_slave(port, slave).with_response_timeout(TokioDelay, timeout_ms))
+}
+
+#[tokio::main]
+async fn main() -> CliResult<()> {
+    let cli = Cli::parse();
+    if let Command::Decode {
+        file,
+        format,
+        gap_us,
+    } = &cli.command
+    {
+        let gap_us = gap_us.unwrap_or_else(|| capture::frame_gap_us(cli.baud));
+        return capture::run(file, *format, gap_us);
+    }
+    let port = cli.port.as_deref().ok_or("--port is required")?;
+    let mut motor = open(port, cli.baud, cli.slave, cli.timeout_ms)?;
+
+    match cli.command {
+        Command::Scan { first, last } => {
+            let mut found = Vec::new();
+            let mut port = motor.port;
+            for slave in first..=last {
+                let mut probe = OrcaMotor::new_with_slave(port, slave)
+                    .with_response_timeout(TokioDelay, cli.timeout_ms);
+                if let Ok(mode) = probe.read_mode().await {
+                    found.push(serde_json::json!({ "slave": slave, "mode": mode }));
+                }
+                port = probe.port;
+            }
+            print(&found)?;
+        }
+        Command::Info => print(&read_info(&mut motor).await?)?,
+        Command::Mode { action } => match action {
+            ModeAction::Get => print(&motor.read_mode().await?)?,
+            ModeAction::Set { mode } => {
+                let mode: OrcaModeOfOperation =
+                    serde_json::from_value(serde_json::Value::String(mode))?;
+                motor.set_mode(mode).await?;
+                print(&motor.read_mode().await?)?;
+            }
+        },
+        Command::Reg { action } => match action {
+            RegAction::Read { register, count } => {
+                let address = parse_register(&register)?;
+                let mut values = vec![0; usize::from(count)];
+                motor.read_holdings_into(address, &mut values).await?;
+                let registers: Vec<_> = (address..)
+                    .zip(values)
+                    .map(|(address, value)| RegisterValue { address, value })
+                    .collect();
+                print(&registers)?;
+            }
+            RegAction::Write { register, values } => {
+                let address = parse_register(&register)?;
+                match values[..] {
+                    [value] => motor.write_holding(address, value).await?,
+                    _ => motor.write_holdings(address, &values).await?,
+                }
+            }
+        },
+        Command::Config { action } => match action {
+            ConfigAction::Dump => print(&Config {
+                current_gains: motor.current_gains().await?,
+                position_gains: motor.position_gains().await?,
+                safety_limits: motor.safety_limits().await?,
+            })?,
+            ConfigAction::Load { file, save } => {
+                let config: Config = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+                motor.set_current_gains(config.current_gains).await?;
+                motor.set_position_gains(config.position_gains).await?;
+                motor.set_safety_limits(config.safety_limits).await?;
+                if save {
+                    let mut groups = SaveGroups::default();
+                    groups.tuning = true;
+                    groups.user_options = true;
+                    let flash_timeout_ms = 5000;
+                    motor
+                        .save(groups, &mut TokioDelay, flash_timeout_ms)
+                        .await?;
+                }
+            }
+        },
+        Command::Zero => motor.zero_here().await?,
+        Command::ClearErrors => print(&motor.clear_errors().await?)?,
+        Command::Stream {
+            target,
+            value,
+            duration_ms,
+            period_ms,
+        } => {
+            motor.enable_high_speed(cli.baud, 0).await?;
+            let streamed: CliResult<()> = async {
+                let start = Instant::now();
+                let mut ticks = tokio::time::interval(Duration::from_millis(period_ms));
+                while start.elapsed() < Duration::from_millis(duration_ms) {
+                    ticks.tick().await;
+                    let response = match target {
+                        StreamTarget::Position => motor.send_position_high_speed(value).await?,
+                        StreamTarget::Force => motor.send_force_high_speed(value).await?,
+                    };
+                    print(&response)?;
+                }
+                Ok(())
+            }
+            .await;
+            // stop streaming even if the loop failed, so no stale setpoint is left running
+            let disabled = motor.disable_high_speed().await;
+            streamed?;
+            disabled?;
+        }
+        Command::Monitor { interval_ms } => {
+            let mut ticks = tokio::time::interval(Duration::from_millis(interval_ms));
+            loop {
+                ticks.tick().await;
+                print(&read_info(&mut motor).await?)?;
+            }
+        }
+        Command::Decode { .. } => unreachable!("decoded before opening the port"),
+        #[cfg(feature = "tui")]
+        Command::Dashboard { others } => {
+            let mut buses = vec![dashboard::Bus::new(port, motor, cli.slave)];
+            for other in others {
+                let (port, slave) = match other.rsplit_once(':') {
+                    Some((port, slave)) => (port, slave.parse()?),
+                    None => (other.as_str(), 1),
+                };
+                // each port is opened once, however many motors are on it
+                match buses.iter_mut().find(|bus| bus.port == port) {
+                    Some(bus) => bus.slaves.push(slave),
+                    None => {
+                        let motor = open(port, cli.baud, slave, cli.timeout_ms)?;
+                        buses.push(dashboard::Bus::new(port, motor, slave));
+                    }
+                }
+            }
+            dashboard::run(buses, cli.baud).await?;
+        }
+    }
+    Ok(())
+}
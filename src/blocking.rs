@@ -0,0 +1,348 @@
+//! Blocking driver for ports that implement the `embedded_io` traits.
+//!
+//! [`OrcaMotor`] drives the async [`crate::OrcaMotor`] over a [`BlockingPort`]. Every read and
+//! write completes before its future is first polled, so each call runs to completion on the
+//! spot and shares all framing, validation and error tracking with the async driver.
+//!
+//! The flip side is that nothing can cut a call short: an [`crate::estop::EStop`] trip is only
+//! acted on between calls, never during a blocking read. See [`OrcaMotor::with_estop`].
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::Result;
+use crate::framing::Framing;
+use crate::pdu_payload::*;
+use crate::register_map::OrcaModeOfOperation;
+use crate::safety::OrcaSafetyLimits;
+use crate::stats::OrcaStats;
+use crate::tuning::{CurrentGains, PositionGains};
+use crate::zeroing::{AutoZeroOutcome, AutoZeroParams, AutoZeroProgress};
+
+#[cfg(feature = "alloc")]
+use crate::estop::EStop;
+#[cfg(feature = "alloc")]
+use crate::event::OrcaEvent;
+#[cfg(not(feature = "alloc"))]
+use crate::event::OrcaEventHandler;
+#[cfg(feature = "record")]
+use crate::record::Recorder;
+#[cfg(feature = "units")]
+use crate::units::{Micrometers, Microseconds, Millinewtons};
+
+/// Polls `future` until it completes.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Exposes a blocking port through the async `embedded_io_async` traits.
+pub struct BlockingPort<T>(pub T);
+
+impl<T: embedded_io::ErrorType> embedded_io::ErrorType for BlockingPort<T> {
+    type Error = T::Error;
+}
+
+impl<T: embedded_io::Read> embedded_io_async::Read for BlockingPort<T> {
+    async fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        self.0.read(buf)
+    }
+}
+
+impl<T: embedded_io::Write> embedded_io_async::Write for BlockingPort<T> {
+    async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        self.0.write(buf)
+    }
+    async fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+/// Exposes a blocking delay through the async `DelayNs`.
+struct BlockingDelay<'a, D>(&'a mut D);
+
+impl<D: embedded_hal::delay::DelayNs> embedded_hal_async::delay::DelayNs for BlockingDelay<'_, D> {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.delay_ns(ns)
+    }
+    async fn delay_us(&mut self, us: u32) {
+        self.0.delay_us(us)
+    }
+    async fn delay_ms(&mut self, ms: u32) {
+        self.0.delay_ms(ms)
+    }
+}
+
+/// Blocking counterpart of [`crate::OrcaMotor`], with the same methods.
+pub struct OrcaMotor<T> {
+    inner: crate::OrcaMotor<BlockingPort<T>>,
+}
+
+/// Forwards blocking methods to the async driver.
+macro_rules! blocking {
+    ($($(#[$attr:meta])* fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {$(
+        $(#[$attr])*
+        pub fn $name(&mut self $(, $arg: $ty)*) -> $ret {
+            block_on(self.inner.$name($($arg),*))
+        }
+    )*};
+}
+
+impl<T> OrcaMotor<T>
+where
+    T: embedded_io::Read + embedded_io::Write + Unpin,
+    <T as embedded_io::ErrorType>::Error: Send + Sync + 'static,
+{
+    pub fn new(port: T) -> Self {
+        Self::new_with_slave(port, 1)
+    }
+    pub fn new_with_slave(port: T, slave: u8) -> Self {
+        Self {
+            inner: crate::OrcaMotor::new_with_slave(BlockingPort(port), slave),
+        }
+    }
+
+    /// Links the motor to `estop`.
+    ///
+    /// Unlike the async driver, a trip cannot interrupt a call that is already waiting on the
+    /// port: the blocking read only returns once bytes arrive or the port's own read timeout
+    /// expires. Until then the motor keeps its last command. The trip is acted on when the next
+    /// call starts, which sends the sleep command and fails with [`crate::Error::EStopped`].
+    /// Where a trip must stop the motor at once, use the async driver with
+    /// [`crate::estop::sleep_on_trip`], or give the port a short read timeout.
+    #[cfg(feature = "alloc")]
+    pub fn with_estop(self, estop: &EStop) -> Self {
+        Self {
+            inner: self.inner.with_estop(estop),
+        }
+    }
+
+    pub fn with_framing(self, framing: Framing) -> Self {
+        Self {
+            inner: self.inner.with_framing(framing),
+        }
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.inner.framing()
+    }
+
+    pub fn with_clock(self, clock: fn() -> u64) -> Self {
+        Self {
+            inner: self.inner.with_clock(clock),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn with_event_handler(self, on_event: impl FnMut(&OrcaEvent) + Send + 'static) -> Self {
+        Self {
+            inner: self.inner.with_event_handler(on_event),
+        }
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn with_event_handler(self, on_event: OrcaEventHandler) -> Self {
+        Self {
+            inner: self.inner.with_event_handler(on_event),
+        }
+    }
+
+    #[cfg(feature = "record")]
+    pub fn with_recorder(self, recorder: Recorder) -> Self {
+        Self {
+            inner: self.inner.with_recorder(recorder),
+        }
+    }
+
+    #[cfg(feature = "record")]
+    pub fn recorder_mut(&mut self) -> Option<&mut Recorder> {
+        self.inner.recorder_mut()
+    }
+
+    pub fn stats(&self) -> &OrcaStats {
+        self.inner.stats()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.inner.reset_stats()
+    }
+
+    pub fn port(&mut self) -> &mut T {
+        &mut self.inner.port.0
+    }
+
+    pub fn into_port(self) -> T {
+        self.inner.port.0
+    }
+
+    pub fn now_us(&self) -> u64 {
+        self.inner.now_us()
+    }
+
+    pub fn last_command_us(&self) -> u64 {
+        self.inner.last_command_us()
+    }
+
+    pub fn is_high_speed(&self) -> bool {
+        self.inner.is_high_speed()
+    }
+
+    pub fn errors(&self) -> OrcaErrors {
+        self.inner.errors()
+    }
+
+    pub fn comms_watchdog_ms(&self) -> Option<u16> {
+        self.inner.comms_watchdog_ms()
+    }
+
+    blocking! {
+        fn read_holdings_into(&mut self, address: u16, values: &mut [u16]) -> Result<()>;
+        fn read_holding(&mut self, address: u16) -> Result<u16>;
+        fn read_holding_u32(&mut self, address: u16) -> Result<u32>;
+        fn write_holding(&mut self, address: u16, value: u16) -> Result<()>;
+        fn write_holdings(&mut self, address: u16, values: &[u16]) -> Result<()>;
+        fn read_mode(&mut self) -> Result<OrcaModeOfOperation>;
+        fn set_mode(&mut self, mode: OrcaModeOfOperation) -> Result<()>;
+        fn read_errors(&mut self) -> Result<OrcaErrors>;
+        fn send_high_speed_adu(
+            &mut self,
+            adu: &OrcaHighSpeedRequestADU
+        ) -> Result<OrcaHighSpeedResponsePDU>;
+        fn send_high_speed(
+            &mut self,
+            pdu: OrcaHighSpeedRequestPDU
+        ) -> Result<OrcaHighSpeedResponsePDU>;
+        fn enable_high_speed(
+            &mut self,
+            baud_rate: u32,
+            delay_us: u16
+        ) -> Result<OrcaHighSpeedResponsePDU>;
+        fn disable_high_speed(&mut self) -> Result<OrcaHighSpeedResponsePDU>;
+        fn send_position_high_speed(&mut self, position_um: i32) -> Result<OrcaHighSpeedResponsePDU>;
+        fn send_force_high_speed(&mut self, force_mn: i32) -> Result<OrcaHighSpeedResponsePDU>;
+        fn send_read_high_speed(
+            &mut self,
+            register_address: u16,
+            register_width: u8
+        ) -> Result<OrcaHighSpeedResponsePDU>;
+        fn clear_errors(&mut self) -> Result<OrcaErrors>;
+        fn zero_here(&mut self) -> Result<()>;
+        fn set_position_inverted(&mut self, inverted: bool) -> Result<()>;
+        fn position_inverted(&mut self) -> Result<bool>;
+        fn set_current_gains(&mut self, gains: CurrentGains) -> Result<()>;
+        fn current_gains(&mut self) -> Result<CurrentGains>;
+        fn set_position_gains(&mut self, gains: PositionGains) -> Result<()>;
+        fn position_gains(&mut self) -> Result<PositionGains>;
+        fn set_safety_limits(&mut self, limits: OrcaSafetyLimits) -> Result<()>;
+        fn safety_limits(&mut self) -> Result<OrcaSafetyLimits>;
+        fn arm_comms_watchdog(&mut self, timeout_ms: u16) -> Result<()>;
+        fn keep_alive(&mut self, margin_ms: u32) -> Result<bool>;
+        fn read_position_um(&mut self) -> Result<i32>;
+    }
+
+    #[cfg(feature = "units")]
+    blocking! {
+        fn send_position(&mut self, position: Micrometers) -> Result<OrcaHighSpeedResponsePDU>;
+        fn send_force(&mut self, force: Millinewtons) -> Result<OrcaHighSpeedResponsePDU>;
+        fn read_position(&mut self) -> Result<Micrometers>;
+        fn enable_high_speed_with(
+            &mut self,
+            baud_rate: u32,
+            delay: Microseconds
+        ) -> Result<OrcaHighSpeedResponsePDU>;
+    }
+
+    pub fn read_holdings<const N: usize>(&mut self, address: u16) -> Result<[u16; N]> {
+        block_on(self.inner.read_holdings(address))
+    }
+
+    /// Like [`crate::OrcaMotor::soft_reset`], except that a blocking read cannot be cut short:
+    /// the port's own read timeout is what keeps a silent motor from hanging the call.
+    pub fn soft_reset<D: embedded_hal::delay::DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<OrcaModeOfOperation> {
+        block_on(self.inner.soft_reset(&mut BlockingDelay(delay), timeout_ms))
+    }
+
+    pub fn save<D: embedded_hal::delay::DelayNs>(
+        &mut self,
+        groups: crate::persist::SaveGroups,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        block_on(
+            self.inner
+                .save(groups, &mut BlockingDelay(delay), timeout_ms),
+        )
+    }
+
+    pub fn restore_defaults<D: embedded_hal::delay::DelayNs>(
+        &mut self,
+        groups: crate::persist::DefaultGroups,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        block_on(
+            self.inner
+                .restore_defaults(groups, &mut BlockingDelay(delay), timeout_ms),
+        )
+    }
+
+    pub fn auto_zero<D: embedded_hal::delay::DelayNs>(
+        &mut self,
+        params: AutoZeroParams,
+        delay: &mut D,
+        timeout_ms: u32,
+        on_progress: impl FnMut(AutoZeroProgress),
+    ) -> Result<AutoZeroOutcome> {
+        block_on(
+            self.inner
+                .auto_zero(params, &mut BlockingDelay(delay), timeout_ms, on_progress),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::SaveGroups;
+    use crate::tests::{NoDelay, ScriptedPort};
+
+    #[test]
+    fn blocking_calls_share_the_async_frames() {
+        let mut groups = SaveGroups::default();
+        groups.haptic_config = true;
+        let port = ScriptedPort::default()
+            .get_holding(1, 317, &[3])
+            .set_holding(1, 2, u16::from(groups))
+            .get_holding(1, 2, &[0]);
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(motor.read_mode(), Ok(OrcaModeOfOperation::PositionMode));
+        assert_eq!(motor.save(groups, &mut NoDelay, 1000), Ok(()));
+        assert!(motor.port().is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn trip_is_acted_on_at_the_next_call() {
+        let estop = EStop::new();
+        let port = ScriptedPort::default()
+            .get_holding(1, 317, &[3])
+            .set_holding(1, 3, 1);
+        let mut motor = OrcaMotor::new(port).with_estop(&estop);
+        assert_eq!(motor.read_mode(), Ok(OrcaModeOfOperation::PositionMode));
+
+        estop.trip();
+        assert_eq!(motor.read_mode(), Err(crate::Error::EStopped));
+        assert!(motor.port().is_done());
+    }
+}
@@ -0,0 +1,93 @@
+use core::pin::pin;
+use embedded_hal_async::delay::DelayNs;
+use embedded_registers::Register;
+use futures_util::future::{Either, select};
+
+use crate::framing::ModbusFrame;
+use crate::pdu_payload::OrcaErrors;
+use crate::register_map::*;
+use crate::{Error, OrcaMotor, Result, drain, flags_value};
+
+/// Interval between comms checks while waiting for the motor to come back from a reset.
+pub const RESET_POLL_INTERVAL_MS: u32 = 100;
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    async fn write_ctrl_reg0(&mut self, set: impl FnOnce(&mut CtrlReg0)) -> Result<()> {
+        let mut reg = CtrlReg0::default();
+        set(&mut reg);
+        self.write_holding(CtrlReg0::ADDRESS as u16, flags_value(&reg))
+            .await
+    }
+
+    /// Clears the motor's errors and returns the ones that are still active afterwards.
+    pub async fn clear_errors(&mut self) -> Result<OrcaErrors> {
+        self.write_ctrl_reg0(|r| r.write_clear_errors(true)).await?;
+        self.read_errors().await
+    }
+
+    /// Makes the current shaft position the new zero.
+    pub async fn zero_here(&mut self) -> Result<()> {
+        self.write_ctrl_reg0(|r| r.write_zero_position(true)).await
+    }
+
+    /// Sets which way positive positions point through `PosSign`. The `CtrlReg0` invert bit
+    /// only flips the current sign, so it cannot set a known direction. Save the user options
+    /// to keep the setting across power cycles.
+    pub async fn set_position_inverted(&mut self, inverted: bool) -> Result<()> {
+        self.write_holding(PosSign::ADDRESS as u16, u16::from(inverted))
+            .await
+    }
+
+    pub async fn position_inverted(&mut self) -> Result<bool> {
+        Ok(self.read_holding(PosSign::ADDRESS as u16).await? != 0)
+    }
+
+    /// Resets the motor, then polls `ModeOfOperation` until it answers again.
+    ///
+    /// The reset is sent without waiting for a reply, since the motor may restart before it
+    /// answers. Before each poll the port is drained until it has been quiet for
+    /// [`RESET_POLL_INTERVAL_MS`], and each poll gets as long again to be answered. Errors out if
+    /// the motor has not come back after `timeout_ms`. With the e-stop tripped, the motor is
+    /// put to sleep instead and nothing is reset.
+    pub async fn soft_reset<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<OrcaModeOfOperation> {
+        if self.estop_tripped() {
+            return Err(self.engage_estop().await);
+        }
+        let mut reg = CtrlReg0::default();
+        reg.write_reset(true);
+        let mut bytes = ModbusFrame::new();
+        self.next_request().generate_set_holding(
+            CtrlReg0::ADDRESS as u16,
+            flags_value(&reg),
+            &mut bytes,
+        )?;
+        self.write_frame(&bytes).await?;
+
+        let mut elapsed_ms = 0;
+        loop {
+            drain(&mut self.port, delay, RESET_POLL_INTERVAL_MS).await?;
+            let poll = pin!(self.read_mode());
+            let expired = pin!(delay.delay_ms(RESET_POLL_INTERVAL_MS));
+            if let Either::Left((Ok(mode), _)) = select(poll, expired).await {
+                return Ok(mode);
+            }
+            // a poll cut short leaves its reply to the next drain
+            elapsed_ms += 2 * RESET_POLL_INTERVAL_MS;
+            if elapsed_ms >= timeout_ms {
+                return Err(Error::Timeout {
+                    operation: "Reset",
+                    timeout_ms,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Decoder for captured RS-485 traffic.
+//!
+//! [`split_capture`] cuts timestamped bytes into frames, first at silences on the line and
+//! then at CRC-valid frame boundaries, since a fast slave may answer within the inter-frame
+//! gap. [`decode_frame`] turns each frame into a high-speed PDU or a standard register
+//! request or response.
+
+use alloc::vec::Vec;
+use core::fmt;
+use serde::Serialize;
+
+use crate::pdu_payload::*;
+use crate::register_map::register_name;
+use crate::{Error, Result};
+
+/// Slave address, function code and CRC.
+const MIN_FRAME_LEN: usize = 4;
+/// Longest Modbus RTU frame.
+pub(crate) const MAX_FRAME_LEN: usize = 256;
+
+/// Which side of a transaction a frame is.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Request => Self::Response,
+            Self::Response => Self::Request,
+        }
+    }
+}
+
+/// A frame cut from a capture.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CapturedFrame {
+    /// Arrival of the first byte; 0 if the capture has no timestamps.
+    pub timestamp_us: u64,
+    pub bytes: Vec<u8>,
+    /// Bytes that fit no CRC-valid frame are kept together with `crc_ok` unset.
+    pub crc_ok: bool,
+}
+
+/// Frame lengths implied by the header of a frame starting at `head[0]`.
+pub(crate) fn candidate_lens(head: &[u8]) -> [Option<usize>; 2] {
+    let high_speed = |payload_len| Some(HIGH_SPEED_ADU_OVERHEAD + 1 + payload_len);
+    match head {
+        [_, 0x03, byte_count, ..] => [Some(8), Some(5 + usize::from(*byte_count))],
+        [_, 0x06, ..] | [_, 0x03] => [Some(8), None],
+        [_, 0x10, _, _, _, _, byte_count, ..] => [Some(9 + usize::from(*byte_count)), Some(8)],
+        [_, 0x10, ..] => [None, Some(8)],
+        [_, function, ..] if function & 0x80 != 0 => [Some(5), None],
+        [_, 0x41, ..] => [high_speed(ManageHighSpeedRequestPDUPayload::LEN), None],
+        [_, 0x64, ..] => [
+            high_speed(MotorCommandRequestPDUPayload::LEN),
+            high_speed(MotorCommandResponsePDUPayload::LEN),
+        ],
+        [_, 0x68, ..] => [
+            high_speed(MotorReadRequestPDUPayload::LEN),
+            high_speed(MotorReadResponsePDUPayload::LEN),
+        ],
+        [_, 0x69, ..] => [
+            high_speed(MotorWriteRequestPDUPayload::LEN),
+            high_speed(MotorWriteResponsePDUPayload::LEN),
+        ],
+        _ => [None, None],
+    }
+}
+
+/// Shortest of the ascending `lens` at which `buf` starts with a CRC-valid frame. The CRC is
+/// run once over `buf`, however many lengths are tried.
+pub(crate) fn first_valid_len(buf: &[u8], lens: impl IntoIterator<Item = usize>) -> Option<usize> {
+    let mut digest = crate::crc::Digest::new();
+    let mut fed = 0;
+    lens.into_iter().find(|len| {
+        let Some(bytes) = buf.get(fed..*len) else {
+            return false;
+        };
+        digest.update(bytes);
+        fed = *len;
+        *len >= MIN_FRAME_LEN && digest.matches()
+    })
+}
+
+/// Finds the first CRC-valid frame in `buf` and returns its offset and length.
+///
+/// Frames are matched at the lengths their function code allows. At the start of `buf` any
+/// length is tried as a last resort, so functions this crate does not know still split.
+pub fn find_frame(buf: &[u8]) -> Option<(usize, usize)> {
+    (0..buf.len()).find_map(|offset| {
+        let rest = &buf[offset..];
+        let mut lens = candidate_lens(rest)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        lens.sort_unstable();
+        first_valid_len(rest, lens)
+            .or_else(|| {
+                (offset == 0)
+                    .then(|| first_valid_len(rest, MIN_FRAME_LEN..=rest.len().min(MAX_FRAME_LEN)))
+                    .flatten()
+            })
+            .map(|len| (offset, len))
+    })
+}
+
+/// Splits a burst of back-to-back bytes into frames, each stamped with its first byte's time.
+pub fn split_burst(burst: &[(u64, u8)]) -> Vec<CapturedFrame> {
+    let bytes: Vec<u8> = burst.iter().map(|(_, byte)| *byte).collect();
+    let chunk = |start: usize, end: usize, crc_ok| CapturedFrame {
+        timestamp_us: burst[start].0,
+        bytes: bytes[start..end].to_vec(),
+        crc_ok,
+    };
+    let mut frames = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        match find_frame(&bytes[at..]) {
+            Some((skip, len)) => {
+                if skip > 0 {
+                    frames.push(chunk(at, at + skip, false));
+                }
+                frames.push(chunk(at + skip, at + skip + len, true));
+                at += skip + len;
+            }
+            None => {
+                frames.push(chunk(at, bytes.len(), false));
+                break;
+            }
+        }
+    }
+    frames
+}
+
+/// Splits timestamped bytes into frames, starting a new burst after every silence of at least
+/// `gap_us`. Modbus RTU requires 3.5 character times between frames.
+pub fn split_capture(bytes: &[(u64, u8)], gap_us: u64) -> Vec<CapturedFrame> {
+    bytes
+        .chunk_by(|a, b| b.0.saturating_sub(a.0) < gap_us)
+        .flat_map(split_burst)
+        .collect()
+}
+
+/// A frame decoded as one side of a high-speed or standard register transaction.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct DecodedFrame {
+    pub direction: Direction,
+    pub slave: u8,
+    pub pdu: DecodedPdu,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum DecodedPdu {
+    HighSpeedRequest(OrcaHighSpeedRequestPDU),
+    HighSpeedResponse(OrcaHighSpeedResponsePDU),
+    ReadHoldings {
+        address: u16,
+        count: u16,
+    },
+    ReadHoldingsResponse {
+        values: Vec<u16>,
+    },
+    /// A Write Single Register request, or its echo.
+    WriteHolding {
+        address: u16,
+        value: u16,
+    },
+    WriteHoldings {
+        address: u16,
+        values: Vec<u16>,
+    },
+    WriteHoldingsResponse {
+        address: u16,
+        count: u16,
+    },
+    Exception {
+        function: u8,
+        code: u8,
+    },
+}
+
+/// Decodes a frame, trying it as `prefer` first. Frames that read both ways, like high-speed
+/// manage frames and single register writes, are taken as `prefer`.
+pub fn decode_frame(bytes: &[u8], prefer: Direction) -> Result<DecodedFrame> {
+    if bytes.len() < MIN_FRAME_LEN {
+        return Err(Error::Malformed);
+    }
+    if !check_adu_crc(bytes) {
+        return Err(Error::Crc);
+    }
+    decode_as(bytes, prefer).or_else(|_| decode_as(bytes, prefer.other()))
+}
+
+fn decode_as(bytes: &[u8], direction: Direction) -> Result<DecodedFrame> {
+    let slave = bytes[0];
+    let body = &bytes[1..bytes.len() - 2];
+    let word = |hi: u8, lo: u8| u16::from_be_bytes([hi, lo]);
+    let words = |data: &[u8]| data.chunks_exact(2).map(|w| word(w[0], w[1])).collect();
+    let pdu = match (direction, body) {
+        (Direction::Response, [function, code]) if function & 0x80 != 0 => DecodedPdu::Exception {
+            function: function & 0x7F,
+            code: *code,
+        },
+        (Direction::Request, [0x03, a0, a1, c0, c1]) => DecodedPdu::ReadHoldings {
+            address: word(*a0, *a1),
+            count: word(*c0, *c1),
+        },
+        (Direction::Response, [0x03, byte_count, data @ ..])
+            if usize::from(*byte_count) == data.len() && data.len() % 2 == 0 =>
+        {
+            DecodedPdu::ReadHoldingsResponse {
+                values: words(data),
+            }
+        }
+        (_, [0x06, a0, a1, v0, v1]) => DecodedPdu::WriteHolding {
+            address: word(*a0, *a1),
+            value: word(*v0, *v1),
+        },
+        (Direction::Request, [0x10, a0, a1, c0, c1, byte_count, data @ ..])
+            if usize::from(*byte_count) == data.len()
+                && data.len() == 2 * usize::from(word(*c0, *c1)) =>
+        {
+            DecodedPdu::WriteHoldings {
+                address: word(*a0, *a1),
+                values: words(data),
+            }
+        }
+        (Direction::Response, [0x10, a0, a1, c0, c1]) => DecodedPdu::WriteHoldingsResponse {
+            address: word(*a0, *a1),
+            count: word(*c0, *c1),
+        },
+        (Direction::Request, _) => {
+            DecodedPdu::HighSpeedRequest(OrcaHighSpeedRequestADU::from_bytes(bytes)?.pdu)
+        }
+        (Direction::Response, _) => {
+            DecodedPdu::HighSpeedResponse(OrcaHighSpeedResponseADU::from_bytes(bytes)?.pdu)
+        }
+    };
+    Ok(DecodedFrame {
+        direction,
+        slave,
+        pdu,
+    })
+}
+
+/// A register address, with its name where `register_map` has one.
+struct RegisterLabel(u16);
+
+impl fmt::Display for RegisterLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match register_name(self.0) {
+            Some(name) => write!(f, "{} ({})", name, self.0),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+struct Telemetry<'a>(&'a MotorCommandResponsePDUPayload);
+
+impl fmt::Display for Telemetry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let t = self.0;
+        write!(
+            f,
+            "{} um, {} mN, {} W, {} C, {} mV, errors [",
+            t.position_um, t.force_mn, t.power_w, t.temperature_c, t.voltage_mv
+        )?;
+        for (i, flag) in t.error.active().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            write!(f, "{}{}", separator, flag)?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl fmt::Display for DecodedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "slave {} ", self.slave)?;
+        match &self.pdu {
+            DecodedPdu::HighSpeedRequest(pdu) => match pdu {
+                OrcaHighSpeedRequestPDU::Manage(p) => write!(
+                    f,
+                    "high-speed {:?} at {} baud, {} us delay",
+                    p.sub_function_code, p.baud_rate, p.delay_us
+                ),
+                OrcaHighSpeedRequestPDU::Command(p) => write!(f, "high-speed {:?}", p),
+                OrcaHighSpeedRequestPDU::Read(p) => write!(
+                    f,
+                    "high-speed read {} width {}",
+                    RegisterLabel(p.register_address),
+                    p.register_width
+                ),
+                OrcaHighSpeedRequestPDU::Write(p) => write!(
+                    f,
+                    "high-speed write {} = {} width {}",
+                    RegisterLabel(p.register_address),
+                    p.register_data,
+                    p.register_width
+                ),
+            },
+            DecodedPdu::HighSpeedResponse(pdu) => match pdu {
+                OrcaHighSpeedResponsePDU::Manage(p) => write!(
+                    f,
+                    "high-speed {:?} at {} baud, {} us delay",
+                    p.state_command, p.baud_rate, p.delay_us
+                ),
+                OrcaHighSpeedResponsePDU::Command(p) => write!(f, "telemetry {}", Telemetry(p)),
+                OrcaHighSpeedResponsePDU::Read(p) => write!(
+                    f,
+                    "read value {}, {:?}, {}",
+                    p.read_register_value,
+                    p.mode_of_operation,
+                    Telemetry(&p.command_response)
+                ),
+                OrcaHighSpeedResponsePDU::Write(p) => write!(
+                    f,
+                    "written, {:?}, {}",
+                    p.mode_of_operation,
+                    Telemetry(&p.command_response)
+                ),
+            },
+            DecodedPdu::ReadHoldings { address, count } => {
+                write!(f, "read {} from {}", count, RegisterLabel(*address))
+            }
+            DecodedPdu::ReadHoldingsResponse { values } => write!(f, "values {:?}", values),
+            DecodedPdu::WriteHolding { address, value } => {
+                write!(f, "write {} = {}", RegisterLabel(*address), value)
+            }
+            DecodedPdu::WriteHoldings { address, values } => {
+                write!(f, "write {} = {:?}", RegisterLabel(*address), values)
+            }
+            DecodedPdu::WriteHoldingsResponse { address, count } => {
+                write!(f, "wrote {} from {}", count, RegisterLabel(*address))
+            }
+            DecodedPdu::Exception { function, code } => {
+                write!(f, "exception {:#04x} to function {:#04x}", code, function)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rtu;
+    use std::string::ToString;
+    use std::vec;
+
+    fn at(timestamp_us: u64, bytes: &[u8]) -> Vec<(u64, u8)> {
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (timestamp_us + i as u64 * 10, *b))
+            .collect()
+    }
+
+    #[test]
+    fn splits_back_to_back_frames_and_stray_bytes() {
+        let request = OrcaHighSpeedRequestADU::new(
+            1,
+            OrcaHighSpeedRequestPDU::Read(MotorReadRequestPDUPayload {
+                register_address: 317,
+                register_width: 1,
+            }),
+        )
+        .to_vec();
+        let response = rtu(&[1, 0x03, 0x02, 0x00, 0x03]);
+        // the read request and garbage in one burst, the response after a gap
+        let mut capture = at(0, &request);
+        capture.extend(at(100, &[0xAA, 0x55]));
+        capture.extend(at(5000, &response));
+
+        let frames = split_capture(&capture, 1750);
+        assert_eq!(
+            frames,
+            vec![
+                CapturedFrame {
+                    timestamp_us: 0,
+                    bytes: request.clone(),
+                    crc_ok: true
+                },
+                CapturedFrame {
+                    timestamp_us: 100,
+                    bytes: vec![0xAA, 0x55],
+                    crc_ok: false
+                },
+                CapturedFrame {
+                    timestamp_us: 5000,
+                    bytes: response.clone(),
+                    crc_ok: true
+                },
+            ]
+        );
+        assert_eq!(
+            decode_frame(&request, Direction::Request)
+                .unwrap()
+                .to_string(),
+            "slave 1 high-speed read ModeOfOperation (317) width 1"
+        );
+        assert_eq!(
+            decode_frame(&response, Direction::Request),
+            Ok(DecodedFrame {
+                direction: Direction::Response,
+                slave: 1,
+                pdu: DecodedPdu::ReadHoldingsResponse { values: vec![3] },
+            })
+        );
+    }
+
+    #[test]
+    fn ambiguous_frames_follow_the_preferred_direction() {
+        let write = rtu(&[1, 0x06, 0x00, 0x03, 0x00, 0x01]);
+        let decoded = decode_frame(&write, Direction::Response).unwrap();
+        assert_eq!(decoded.direction, Direction::Response);
+        assert_eq!(decoded.to_string(), "slave 1 write CtrlReg3 (3) = 1");
+
+        let mut corrupt = write.clone();
+        corrupt[4] ^= 1;
+        assert_eq!(decode_frame(&corrupt, Direction::Request), Err(Error::Crc));
+    }
+}
@@ -0,0 +1,113 @@
+use core::fmt;
+use embedded_io_async::ReadExactError;
+
+use crate::safety::EnvelopeViolation;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Errors returned by the driver. Plain data, so no allocator is needed to report them.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Error {
+    /// The port failed.
+    Io(embedded_io_async::ErrorKind),
+    /// The port ran dry before a full response arrived.
+    UnexpectedEof,
+    /// rmodbus could not build the request or parse the response, or the motor answered with a
+    /// Modbus exception.
+    Modbus(rmodbus::ErrorKind),
+    /// A high-speed frame failed its CRC check.
+    Crc,
+    /// A high-speed frame was cut short, too long, or carried an undefined code.
+    Malformed,
+    /// The buffer given to an encoder cannot hold the frame.
+    BufferTooSmall,
+    /// The response came from another slave than the one addressed.
+    SlaveMismatch { expected: u8, got: u8 },
+    /// A register read returned another number of registers than requested.
+    RegisterCount { expected: u16, got: u16 },
+    /// `ModeOfOperation` held a value that is not an `OrcaModeOfOperation`.
+    UnknownMode(u8),
+    /// The motor did not finish `operation` within `timeout_ms`.
+    Timeout {
+        operation: &'static str,
+        timeout_ms: u32,
+    },
+    /// A register re-read after a flash write held another value than before it.
+    ReadBack {
+        address: u16,
+        expected: u16,
+        got: u16,
+    },
+    /// A `SoftEnvelope`'s minimum position lies above its maximum.
+    InvalidEnvelope,
+    /// A `SafeMotor` setpoint was refused by its envelope.
+    Rejected {
+        requested: i32,
+        violation: EnvelopeViolation,
+    },
+    /// The motor's e-stop is tripped.
+    EStopped,
+    /// The motor's framing cannot carry the request, e.g. a high-speed frame over Modbus TCP.
+    Unsupported,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(kind) => write!(f, "Port error: {:?}", kind),
+            Self::UnexpectedEof => f.write_str("Port closed mid-frame"),
+            Self::Modbus(kind) => write!(f, "Modbus error: {:?}", kind),
+            Self::Crc => f.write_str("CRC check failed"),
+            Self::Malformed => f.write_str("Malformed frame"),
+            Self::BufferTooSmall => f.write_str("Buffer too small for frame"),
+            Self::SlaveMismatch { expected, got } => {
+                write!(
+                    f,
+                    "Slave address mismatch: expected {}, got {}",
+                    expected, got
+                )
+            }
+            Self::RegisterCount { expected, got } => {
+                write!(f, "Expected {} registers, got {}", expected, got)
+            }
+            Self::UnknownMode(mode) => write!(f, "Unknown mode of operation {}", mode),
+            Self::Timeout {
+                operation,
+                timeout_ms,
+            } => write!(f, "{} did not finish within {} ms", operation, timeout_ms),
+            Self::ReadBack {
+                address,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Register {} read back as {} instead of {}",
+                address, got, expected
+            ),
+            Self::InvalidEnvelope => f.write_str("Envelope minimum position above its maximum"),
+            Self::Rejected {
+                requested,
+                violation,
+            } => write!(f, "Setpoint {} rejected: {:?}", requested, violation),
+            Self::EStopped => f.write_str("Emergency stop engaged"),
+            Self::Unsupported => f.write_str("Not supported by the framing in use"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<rmodbus::ErrorKind> for Error {
+    fn from(kind: rmodbus::ErrorKind) -> Self {
+        Self::Modbus(kind)
+    }
+}
+
+impl<E: embedded_io_async::Error> From<ReadExactError<E>> for Error {
+    fn from(e: ReadExactError<E>) -> Self {
+        match e {
+            ReadExactError::UnexpectedEof => Self::UnexpectedEof,
+            ReadExactError::Other(e) => Self::Io(e.kind()),
+        }
+    }
+}
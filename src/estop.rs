@@ -0,0 +1,167 @@
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::task::Poll;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+use futures_util::task::AtomicWaker;
+
+use crate::OrcaMotor;
+
+struct Inner {
+    tripped: AtomicBool,
+    generation: AtomicU32,
+    /// Wakers of the linked motors and their sleep tasks. Dropped links leave dead entries
+    /// behind, which are pruned on the next registration.
+    wakers: spin::Mutex<Vec<Weak<AtomicWaker>>>,
+}
+
+/// Software emergency stop shared by any number of motors.
+///
+/// Tripping it aborts every transaction in flight on a linked `OrcaMotor`, puts that motor to
+/// sleep, and refuses all further commands until [`EStop::rearm`] is called. A motor that is
+/// idle when the stop trips is put to sleep by its [`sleep_on_trip`] task, or else by its next
+/// call.
+///
+/// An aborted transaction leaves its reply on the way. A motor with a response timeout drains
+/// the port before sending the sleep command, so that reply cannot be mistaken for the answer
+/// to it.
+#[derive(Clone)]
+pub struct EStop {
+    inner: Arc<Inner>,
+}
+
+impl Default for EStop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EStop {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                tripped: AtomicBool::new(false),
+                generation: AtomicU32::new(0),
+                wakers: spin::Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    pub fn trip(&self) {
+        if !self.inner.tripped.swap(true, Ordering::SeqCst) {
+            self.inner.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        self.inner
+            .wakers
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .for_each(|w| w.wake());
+    }
+
+    pub fn rearm(&self) {
+        self.inner.tripped.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.inner.tripped.load(Ordering::SeqCst)
+    }
+
+    /// Number of times the stop has been tripped, so each motor reacts once per trip.
+    pub(crate) fn generation(&self) -> u32 {
+        self.inner.generation.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn register(&self) -> Arc<AtomicWaker> {
+        let waker = Arc::new(AtomicWaker::new());
+        let mut wakers = self.inner.wakers.lock();
+        wakers.retain(|w| w.strong_count() > 0);
+        wakers.push(Arc::downgrade(&waker));
+        waker
+    }
+
+    /// Resolves once the stop has been tripped since it stood at `generation`.
+    async fn tripped_since(&self, generation: u32, waker: &AtomicWaker) {
+        poll_fn(|cx| {
+            if self.generation() != generation {
+                return Poll::Ready(());
+            }
+            waker.register(cx.waker());
+            if self.generation() != generation {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Resolves once the stop is tripped.
+    pub(crate) async fn tripped(&self, waker: &AtomicWaker) {
+        poll_fn(|cx| {
+            if self.is_tripped() {
+                return Poll::Ready(());
+            }
+            waker.register(cx.waker());
+            if self.is_tripped() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// A motor's link to an [`EStop`].
+pub(crate) struct EStopLink {
+    pub(crate) estop: EStop,
+    pub(crate) waker: Arc<AtomicWaker>,
+    /// Last trip this motor has already answered with a sleep command.
+    pub(crate) handled_generation: u32,
+}
+
+/// Puts a shared motor to sleep every time its e-stop trips, even while nothing else is using
+/// it. A trip that aborted a call on the motor has already been answered by that call, so each
+/// trip sends one sleep command either way.
+///
+/// Returns at once if the motor has no e-stop, and never otherwise.
+pub async fn sleep_on_trip<M, T, R>(motor: &Mutex<M, OrcaMotor<T, R>>)
+where
+    M: RawMutex,
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    let (estop, mut handled) = match motor.lock().await.estop.as_ref() {
+        Some(link) => (link.estop.clone(), link.handled_generation),
+        None => return,
+    };
+    let waker = estop.register();
+    loop {
+        estop.tripped_since(handled, &waker).await;
+        let mut motor = motor.lock().await;
+        // the sleep is sent once per trip, by whichever side gets the motor first
+        let _ = motor.engage_estop().await;
+        handled = motor
+            .estop
+            .as_ref()
+            .map_or(estop.generation(), |link| link.handled_generation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_links_are_pruned() {
+        let estop = EStop::new();
+        let _kept = estop.register();
+        drop([estop.register(), estop.register()]);
+        let _also_kept = estop.register();
+        assert_eq!(estop.inner.wakers.lock().len(), 2);
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use serde::{Deserialize, Serialize};
+
+use crate::pdu_payload::{OrcaErrorFlag, OrcaHighSpeedRequestPDU};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub enum OrcaEvent {
+    /// The motor reported `communication_timeout`: it went longer than `UserCommsTimeout`
+    /// without a command.
+    CommsTimeout { slave: u8, timestamp_us: u64 },
+    /// The motor's e-stop tripped and a sleep command was sent to it. `sleep_sent` is false if
+    /// that command failed.
+    EmergencyStop {
+        slave: u8,
+        timestamp_us: u64,
+        sleep_sent: bool,
+    },
+    /// `flag` became active. `command` is the high-speed request whose response showed it, or
+    /// `None` if it was seen through a register read.
+    ErrorRaised {
+        slave: u8,
+        flag: OrcaErrorFlag,
+        timestamp_us: u64,
+        command: Option<OrcaHighSpeedRequestPDU>,
+    },
+    /// `flag`, active since `first_seen_us`, is no longer reported.
+    ErrorCleared {
+        slave: u8,
+        flag: OrcaErrorFlag,
+        first_seen_us: u64,
+        timestamp_us: u64,
+        command: Option<OrcaHighSpeedRequestPDU>,
+    },
+}
+
+#[cfg(feature = "alloc")]
+pub type OrcaEventHandler = Box<dyn FnMut(&OrcaEvent) + Send>;
+
+/// Without `alloc` the handler is a plain function and cannot capture state.
+#[cfg(not(feature = "alloc"))]
+pub type OrcaEventHandler = fn(&OrcaEvent);
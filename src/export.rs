@@ -0,0 +1,397 @@
+//! Flat telemetry tables for analysis tools such as pandas.
+//!
+//! A [`Sample`] is one motor's telemetry from one high-speed cycle, taken from a live response
+//! or, with the `record` feature, from a logged transaction. [`CsvExporter`] writes one row
+//! per sample in the chosen [`Column`]s; with the `parquet` feature [`ParquetExporter`] writes
+//! the same columns to a Parquet file.
+
+use std::format;
+use std::io::{self, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::pdu_payload::*;
+#[cfg(feature = "record")]
+use crate::record::{Exchange, Transaction};
+use crate::register_map::OrcaModeOfOperation;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sample {
+    pub timestamp_us: u64,
+    pub slave: u8,
+    /// Only read and write responses carry the mode.
+    pub mode: Option<OrcaModeOfOperation>,
+    pub telemetry: MotorCommandResponsePDUPayload,
+}
+
+impl Sample {
+    /// The telemetry in `response`, or `None` for a manage response.
+    pub fn new(timestamp_us: u64, slave: u8, response: &OrcaHighSpeedResponsePDU) -> Option<Self> {
+        let mode = match response {
+            OrcaHighSpeedResponsePDU::Read(p) => Some(p.mode_of_operation),
+            OrcaHighSpeedResponsePDU::Write(p) => Some(p.mode_of_operation),
+            _ => None,
+        };
+        Some(Self {
+            timestamp_us,
+            slave,
+            mode,
+            telemetry: *response.command_response()?,
+        })
+    }
+
+    /// The telemetry a logged high-speed exchange returned, timestamped when it arrived.
+    #[cfg(feature = "record")]
+    pub fn from_transaction(transaction: &Transaction) -> Option<Self> {
+        let Exchange::HighSpeed {
+            response: Some(response),
+            ..
+        } = &transaction.exchange
+        else {
+            return None;
+        };
+        let timestamp_us = transaction.timestamp_us + transaction.latency_us;
+        Self::new(timestamp_us, transaction.slave, response)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Field {
+    TimestampUs,
+    Slave,
+    Mode,
+    PositionUm,
+    ForceMn,
+    PowerW,
+    TemperatureC,
+    VoltageMv,
+    /// Whether `flag` is active, as 0 or 1.
+    Error(OrcaErrorFlag),
+}
+
+impl Field {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TimestampUs => "timestamp_us",
+            Self::Slave => "slave",
+            Self::Mode => "mode",
+            Self::PositionUm => "position_um",
+            Self::ForceMn => "force_mn",
+            Self::PowerW => "power_w",
+            Self::TemperatureC => "temperature_c",
+            Self::VoltageMv => "voltage_mv",
+            Self::Error(flag) => flag.name(),
+        }
+    }
+
+    /// The field's value in `sample`, or `None` for the mode and error flags.
+    fn number(self, sample: &Sample) -> Option<i64> {
+        let t = &sample.telemetry;
+        Some(match self {
+            Self::TimestampUs => sample.timestamp_us as i64,
+            Self::Slave => sample.slave.into(),
+            Self::PositionUm => t.position_um.into(),
+            Self::ForceMn => t.force_mn.into(),
+            Self::PowerW => t.power_w.into(),
+            Self::TemperatureC => t.temperature_c.into(),
+            Self::VoltageMv => t.voltage_mv.into(),
+            Self::Mode | Self::Error(_) => return None,
+        })
+    }
+}
+
+fn mode_name(mode: OrcaModeOfOperation) -> String {
+    format!("{:?}", mode)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Column {
+    pub field: Field,
+    pub header: String,
+    /// Factor applied to numeric fields, which are then written as floats.
+    pub scale: Option<f64>,
+}
+
+impl Column {
+    pub fn new(field: Field) -> Self {
+        Self {
+            field,
+            header: field.name().into(),
+            scale: None,
+        }
+    }
+
+    /// Multiplies the field by `factor` and heads the column `header`, as in
+    /// `Column::new(Field::PositionUm).scaled("position_mm", 1e-3)`.
+    pub fn scaled(mut self, header: impl Into<String>, factor: f64) -> Self {
+        self.header = header.into();
+        self.scale = Some(factor);
+        self
+    }
+
+    /// Every field unscaled: timestamp, slave, mode, telemetry, then one column per error flag.
+    pub fn all() -> Vec<Self> {
+        let fields = [
+            Field::TimestampUs,
+            Field::Slave,
+            Field::Mode,
+            Field::PositionUm,
+            Field::ForceMn,
+            Field::PowerW,
+            Field::TemperatureC,
+            Field::VoltageMv,
+        ];
+        let flags = OrcaErrorFlag::ALL.map(Field::Error);
+        fields.into_iter().chain(flags).map(Self::new).collect()
+    }
+
+    fn csv_value(&self, sample: &Sample) -> String {
+        match self.field {
+            Field::Mode => sample.mode.map(mode_name).unwrap_or_default(),
+            Field::Error(flag) => {
+                if sample.telemetry.error.contains(flag) {
+                    "1".into()
+                } else {
+                    "0".into()
+                }
+            }
+            field => {
+                let value = field.number(sample).unwrap_or_default();
+                match self.scale {
+                    Some(factor) => format!("{}", value as f64 * factor),
+                    None => format!("{}", value),
+                }
+            }
+        }
+    }
+}
+
+/// Quotes `field` if it holds a delimiter, quote or line break.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+/// Writes samples as CSV, one row per sample, after a header row.
+pub struct CsvExporter<W> {
+    out: W,
+    columns: Vec<Column>,
+    header_written: bool,
+}
+
+impl<W: Write> CsvExporter<W> {
+    pub fn new(out: W, columns: Vec<Column>) -> Self {
+        Self {
+            out,
+            columns,
+            header_written: false,
+        }
+    }
+
+    pub fn write(&mut self, sample: &Sample) -> io::Result<()> {
+        if !self.header_written {
+            let header: Vec<_> = self.columns.iter().map(|c| csv_escape(&c.header)).collect();
+            writeln!(self.out, "{}", header.join(","))?;
+            self.header_written = true;
+        }
+        let row: Vec<_> = self.columns.iter().map(|c| c.csv_value(sample)).collect();
+        writeln!(self.out, "{}", row.join(","))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+#[cfg(feature = "parquet")]
+pub use self::parquet_export::ParquetExporter;
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::vec::Vec;
+
+    use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+    use arrow_schema::{DataType, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+    use parquet::errors::Result;
+
+    use super::{Column, Field, Sample, mode_name};
+
+    /// Rows buffered before they are written out as one record batch.
+    const BATCH_ROWS: usize = 4096;
+
+    impl Column {
+        fn data_type(&self) -> DataType {
+            match (self.field, self.scale) {
+                (Field::Mode, _) => DataType::Utf8,
+                (Field::Error(_), _) => DataType::Boolean,
+                (_, Some(_)) => DataType::Float64,
+                (_, None) => DataType::Int64,
+            }
+        }
+
+        fn array(&self, samples: &[Sample]) -> ArrayRef {
+            let numbers = samples
+                .iter()
+                .map(|s| self.field.number(s).unwrap_or_default());
+            match (self.field, self.scale) {
+                (Field::Mode, _) => Arc::new(
+                    samples
+                        .iter()
+                        .map(|s| s.mode.map(mode_name))
+                        .collect::<StringArray>(),
+                ),
+                (Field::Error(flag), _) => Arc::new(
+                    samples
+                        .iter()
+                        .map(|s| Some(s.telemetry.error.contains(flag)))
+                        .collect::<BooleanArray>(),
+                ),
+                (_, Some(factor)) => Arc::new(Float64Array::from_iter_values(
+                    numbers.map(|value| value as f64 * factor),
+                )),
+                (_, None) => Arc::new(Int64Array::from_iter_values(numbers)),
+            }
+        }
+    }
+
+    /// Writes samples to a Parquet file with one column per [`Column`].
+    pub struct ParquetExporter<W: Write + Send> {
+        writer: ArrowWriter<W>,
+        schema: SchemaRef,
+        columns: Vec<Column>,
+        samples: Vec<Sample>,
+    }
+
+    impl<W: Write + Send> ParquetExporter<W> {
+        pub fn new(out: W, columns: Vec<Column>) -> Result<Self> {
+            let fields: Vec<_> = columns
+                .iter()
+                .map(|c| {
+                    let nullable = c.field == Field::Mode;
+                    arrow_schema::Field::new(c.header.clone(), c.data_type(), nullable)
+                })
+                .collect();
+            let schema = Arc::new(Schema::new(fields));
+            Ok(Self {
+                writer: ArrowWriter::try_new(out, schema.clone(), None)?,
+                schema,
+                columns,
+                samples: Vec::with_capacity(BATCH_ROWS),
+            })
+        }
+
+        pub fn write(&mut self, sample: &Sample) -> Result<()> {
+            self.samples.push(*sample);
+            if self.samples.len() == BATCH_ROWS {
+                self.flush_batch()?;
+            }
+            Ok(())
+        }
+
+        /// Writes the buffered rows and the file footer.
+        pub fn finish(mut self) -> Result<W> {
+            self.flush_batch()?;
+            self.writer.into_inner()
+        }
+
+        fn flush_batch(&mut self) -> Result<()> {
+            if self.samples.is_empty() {
+                return Ok(());
+            }
+            let arrays = self.columns.iter().map(|c| c.array(&self.samples));
+            let batch = RecordBatch::try_new(self.schema.clone(), arrays.collect())?;
+            self.writer.write(&batch)?;
+            self.samples.clear();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+
+    fn sample() -> Sample {
+        let mut telemetry = MotorCommandResponsePDUPayload {
+            position_um: 1500,
+            force_mn: -20,
+            power_w: 3,
+            temperature_c: 30,
+            voltage_mv: 24000,
+            error: OrcaErrors::default(),
+        };
+        telemetry.error.force_clipping = true;
+        let response = OrcaHighSpeedResponsePDU::Read(MotorReadResponsePDUPayload {
+            read_register_value: 3,
+            mode_of_operation: OrcaModeOfOperation::PositionMode,
+            command_response: telemetry,
+        });
+        Sample::new(2000, 1, &response).unwrap()
+    }
+
+    #[test]
+    fn csv_rows_follow_columns() {
+        let columns = vec![
+            Column::new(Field::TimestampUs).scaled("time_s", 1e-6),
+            Column::new(Field::Slave),
+            Column::new(Field::Mode),
+            Column::new(Field::PositionUm).scaled("position_mm", 1e-3),
+            Column::new(Field::ForceMn),
+            Column::new(Field::Error(OrcaErrorFlag::ForceClipping)),
+            Column::new(Field::Error(OrcaErrorFlag::PowerExceeded)),
+        ];
+        let mut csv = CsvExporter::new(Vec::new(), columns);
+        csv.write(&sample()).unwrap();
+        csv.write(&sample()).unwrap();
+        let text = String::from_utf8(csv.into_inner()).unwrap();
+        let row = "0.002,1,PositionMode,1.5,-20,1,0\n";
+        assert_eq!(
+            text,
+            format!(
+                "time_s,slave,mode,position_mm,force_mn,force_clipping,power_exceeded\n{}{}",
+                row, row
+            )
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_file_reads_back() {
+        use arrow_array::{Array, Float64Array, StringArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let columns = vec![
+            Column::new(Field::Mode),
+            Column::new(Field::PositionUm).scaled("position_mm", 1e-3),
+        ];
+        let path = std::env::temp_dir().join(format!("orca-export-{}.parquet", std::process::id()));
+        let mut parquet =
+            ParquetExporter::new(std::fs::File::create(&path).unwrap(), columns).unwrap();
+        parquet.write(&sample()).unwrap();
+        parquet.finish().unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        let mode = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        let position = batch.column(1).as_any().downcast_ref::<Float64Array>();
+        assert_eq!(mode.value(0), "PositionMode");
+        assert_eq!(position.unwrap().value(0), 1.5);
+        assert_eq!(batch.num_rows(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
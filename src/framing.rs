@@ -0,0 +1,186 @@
+use rmodbus::ModbusProto;
+use serde::{Deserialize, Serialize};
+
+/// Longest standard Modbus ADU in binary form: a Modbus TCP frame.
+pub const MAX_ADU_LEN: usize = 260;
+
+/// Longest Modbus ASCII ADU on the wire.
+pub const MAX_ASCII_ADU_LEN: usize = 513;
+
+/// A standard Modbus ADU held on the stack. ASCII frames are kept decoded, LRC included.
+pub type ModbusFrame = heapless::Vec<u8, MAX_ADU_LEN>;
+
+/// How standard register requests are framed on the port.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
+pub enum Framing {
+    /// Modbus RTU on a serial line.
+    #[default]
+    Rtu,
+    /// RTU frames tunnelled unchanged through a TCP connection, as RS-485-to-Ethernet gateways
+    /// do. High-speed frames pass through the same way.
+    ///
+    /// On the wire this is exactly [`Framing::Rtu`]: the gateway forwards the serial bytes, CRC
+    /// included, and adds no header of its own. The driver already reads frames by length
+    /// rather than by line silence, so a stream that splits or joins them changes nothing. The
+    /// variant is an alias kept so configurations and recordings say which transport was used.
+    RtuOverTcp,
+    /// Modbus TCP: MBAP header, no CRC.
+    Tcp,
+    /// Modbus ASCII: hex characters between `:` and CRLF, checked by LRC.
+    Ascii,
+}
+
+impl Framing {
+    /// The framing whose bytes are sent, with aliases resolved.
+    pub const fn wire(self) -> Self {
+        match self {
+            Self::RtuOverTcp => Self::Rtu,
+            other => other,
+        }
+    }
+
+    pub fn proto(self) -> ModbusProto {
+        match self {
+            Self::Rtu | Self::RtuOverTcp => ModbusProto::Rtu,
+            Self::Tcp => ModbusProto::TcpUdp,
+            Self::Ascii => ModbusProto::Ascii,
+        }
+    }
+
+    /// High-speed frames are raw RTU frames, so only RTU framings can carry them.
+    pub fn carries_high_speed(self) -> bool {
+        self.wire() == Self::Rtu
+    }
+
+    /// Bytes to read before rmodbus can tell a response's length. ASCII responses are read up
+    /// to their line feed instead.
+    pub(crate) fn header_len(self) -> Option<usize> {
+        match self {
+            Self::Rtu | Self::RtuOverTcp => Some(3),
+            Self::Tcp => Some(6),
+            Self::Ascii => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::register_map::OrcaModeOfOperation;
+    use crate::tests::ScriptedPort;
+    use crate::{Error, OrcaMotor};
+    use futures::executor::block_on;
+    use std::vec::Vec;
+
+    /// Encodes `frame` as a Modbus ASCII line with its LRC.
+    fn ascii(frame: &[u8]) -> Vec<u8> {
+        let lrc = frame
+            .iter()
+            .fold(0u8, |a, b| a.wrapping_add(*b))
+            .wrapping_neg();
+        let mut line = std::string::String::from(":");
+        frame
+            .iter()
+            .chain([lrc].iter())
+            .for_each(|b| line.push_str(&std::format!("{:02X}", b)));
+        line.push_str("\r\n");
+        line.into_bytes()
+    }
+
+    #[test]
+    fn read_mode_over_modbus_tcp() {
+        let port = ScriptedPort::default().expect(
+            std::vec![0, 2, 0, 0, 0, 6, 1, 0x03, 0x01, 0x3D, 0x00, 0x01],
+            std::vec![0, 2, 0, 0, 0, 5, 1, 0x03, 0x02, 0x00, 0x03],
+        );
+        let mut motor = OrcaMotor::new(port).with_framing(Framing::Tcp);
+        assert_eq!(
+            block_on(motor.read_mode()),
+            Ok(OrcaModeOfOperation::PositionMode)
+        );
+        assert_eq!(
+            block_on(motor.send_position_high_speed(0)),
+            Err(Error::Unsupported)
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn read_mode_over_modbus_ascii() {
+        let port = ScriptedPort::default().expect(
+            ascii(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+            ascii(&[1, 0x03, 0x02, 0x00, 0x03]),
+        );
+        let mut motor = OrcaMotor::new(port).with_framing(Framing::Ascii);
+        assert_eq!(
+            block_on(motor.read_mode()),
+            Ok(OrcaModeOfOperation::PositionMode)
+        );
+        assert!(motor.port.is_done());
+    }
+
+    /// Motor over a real TCP connection to a local stand-in for an RS-485-to-Ethernet gateway.
+    #[cfg(feature = "alloc")]
+    #[tokio::test]
+    async fn rtu_over_tcp_through_a_local_gateway() {
+        use crate::pdu_payload::*;
+        use crate::tests::rtu;
+        use embedded_io_adapters::tokio_1::FromTokio;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::{TcpListener, TcpStream};
+
+        let telemetry = MotorCommandResponsePDUPayload {
+            position_um: 12000,
+            force_mn: 0,
+            power_w: 0,
+            temperature_c: 24,
+            voltage_mv: 24000,
+            error: OrcaErrors::default(),
+        };
+        let high_speed = OrcaHighSpeedRequestADU::new(
+            1,
+            OrcaHighSpeedRequestPDU::Command(
+                MotorCommandRequestPDUPayload::PositionControlStream { position_um: 12000 },
+            ),
+        );
+        let script = [
+            (
+                rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+                rtu(&[1, 0x03, 0x02, 0x00, 0x03]),
+            ),
+            (
+                high_speed.to_vec(),
+                OrcaHighSpeedResponseADU::new(1, OrcaHighSpeedResponsePDU::Command(telemetry))
+                    .to_vec(),
+            ),
+        ];
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for (request, response) in script {
+                let mut got = std::vec![0; request.len()];
+                socket.read_exact(&mut got).await.unwrap();
+                assert_eq!(got, request);
+                // answer in two segments, as a gateway flushing its serial buffer may
+                let (head, tail) = response.split_at(2);
+                socket.write_all(head).await.unwrap();
+                socket.flush().await.unwrap();
+                socket.write_all(tail).await.unwrap();
+            }
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut motor = OrcaMotor::new(FromTokio::new(stream)).with_framing(Framing::RtuOverTcp);
+        assert_eq!(
+            motor.read_mode().await,
+            Ok(OrcaModeOfOperation::PositionMode)
+        );
+        assert_eq!(
+            motor.send_position_high_speed(12000).await,
+            Ok(OrcaHighSpeedResponsePDU::Command(telemetry))
+        );
+        gateway.await.unwrap();
+    }
+}
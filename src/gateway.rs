@@ -0,0 +1,393 @@
+//! Modbus TCP server that exposes ORCA motors to SCADA and PLC tools.
+//!
+//! Each unit id maps to a slave on a bus: one [`OrcaMotor`] per port, behind the same
+//! `embassy_sync` [`Mutex`] that [`crate::watchdog::keepalive_task`] takes. Share that handle
+//! with the task streaming high-speed commands: every forwarded request holds the lock for one
+//! transaction, so gateway traffic slots in between stream commands instead of colliding with
+//! them on the wire. Slaves sharing a port share the one motor, which is pointed at each slave
+//! only for the length of its transaction.
+//!
+//! Requests are bounded by the motor's own response timeout (see
+//! [`OrcaMotor::with_response_timeout`]), so a silent slave costs one timeout and the bus is
+//! drained before the next transaction. Buses without one wait for the motor indefinitely.
+//!
+//! Read Holding Registers (0x03), Write Single Register (0x06) and Write Multiple Registers
+//! (0x10) are forwarded. Other functions are answered with an Illegal Function exception.
+
+use core::convert::Infallible;
+use core::ops::{Deref, DerefMut};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::vec::Vec;
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embedded_hal_async::delay::DelayNs;
+use futures_util::StreamExt;
+use futures_util::stream::FuturesUnordered;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{Error, MAX_READ_REGISTERS, NoTimeout, OrcaMotor};
+
+/// MBAP header length, unit id included.
+const MBAP_LEN: usize = 7;
+/// Largest MBAP length field: the unit id plus a 253-byte PDU.
+const MAX_MBAP_LENGTH: usize = 254;
+/// Most registers a single Write Multiple Registers request can carry.
+const MAX_WRITE_REGISTERS: usize = 123;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+const SERVER_DEVICE_FAILURE: u8 = 0x04;
+const GATEWAY_PATH_UNAVAILABLE: u8 = 0x0A;
+const GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+/// A motor shared between the gateway and the application's own tasks.
+pub type SharedMotor<M, T, R = NoTimeout> = Arc<Mutex<M, OrcaMotor<T, R>>>;
+
+/// Where requests for a unit id go.
+struct Route<M: RawMutex, T, R> {
+    bus: SharedMotor<M, T, R>,
+    /// Slave to address on `bus`, or `None` for the motor's own.
+    slave: Option<u8>,
+}
+
+/// The bus motor, pointed at one slave until dropped.
+struct Addressed<'a, M: RawMutex, T, R> {
+    motor: MutexGuard<'a, M, OrcaMotor<T, R>>,
+    home: u8,
+}
+
+impl<'a, M: RawMutex, T, R> Addressed<'a, M, T, R> {
+    fn new(mut motor: MutexGuard<'a, M, OrcaMotor<T, R>>, slave: Option<u8>) -> Self {
+        let home = motor.mreq.unit_id;
+        motor.mreq.unit_id = slave.unwrap_or(home);
+        Self { motor, home }
+    }
+}
+
+impl<M: RawMutex, T, R> Deref for Addressed<'_, M, T, R> {
+    type Target = OrcaMotor<T, R>;
+    fn deref(&self) -> &Self::Target {
+        &self.motor
+    }
+}
+
+impl<M: RawMutex, T, R> DerefMut for Addressed<'_, M, T, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.motor
+    }
+}
+
+impl<M: RawMutex, T, R> Drop for Addressed<'_, M, T, R> {
+    fn drop(&mut self) {
+        // the application's own tasks keep talking to the motor's slave
+        self.motor.mreq.unit_id = self.home;
+    }
+}
+
+/// A standard register request decoded from a Modbus TCP PDU.
+enum Request {
+    ReadHoldings { address: u16, count: u16 },
+    WriteHolding { address: u16, value: u16 },
+    WriteHoldings { address: u16, values: Vec<u16> },
+}
+
+impl Request {
+    fn parse(pdu: &[u8]) -> core::result::Result<Self, u8> {
+        let word = |i: usize| u16::from_be_bytes([pdu[i], pdu[i + 1]]);
+        match pdu.first() {
+            Some(0x03) if pdu.len() == 5 => {
+                let count = word(3);
+                if !(1..=MAX_READ_REGISTERS).contains(&usize::from(count)) {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+                Ok(Self::ReadHoldings {
+                    address: word(1),
+                    count,
+                })
+            }
+            Some(0x06) if pdu.len() == 5 => Ok(Self::WriteHolding {
+                address: word(1),
+                value: word(3),
+            }),
+            Some(0x10) if pdu.len() >= 6 => {
+                let count = usize::from(word(3));
+                if !(1..=MAX_WRITE_REGISTERS).contains(&count)
+                    || usize::from(pdu[5]) != count * 2
+                    || pdu.len() != 6 + count * 2
+                {
+                    return Err(ILLEGAL_DATA_VALUE);
+                }
+                Ok(Self::WriteHoldings {
+                    address: word(1),
+                    values: (0..count).map(|i| word(6 + i * 2)).collect(),
+                })
+            }
+            Some(0x03 | 0x06 | 0x10) => Err(ILLEGAL_DATA_VALUE),
+            _ => Err(ILLEGAL_FUNCTION),
+        }
+    }
+}
+
+/// Exception code reported to the Modbus TCP client for a failed forward.
+fn exception_code(error: Error) -> u8 {
+    match error {
+        // the motor's own exception passes through unchanged
+        Error::Modbus(kind) => match kind.to_modbus_error() {
+            Ok(code) if (0x01..=0x08).contains(&code.byte()) => code.byte(),
+            _ => GATEWAY_TARGET_FAILED,
+        },
+        Error::EStopped => SERVER_DEVICE_FAILURE,
+        _ => GATEWAY_TARGET_FAILED,
+    }
+}
+
+/// Serves Modbus TCP clients on behalf of a set of motors.
+pub struct Gateway<M: RawMutex, T, R = NoTimeout> {
+    routes: BTreeMap<u8, Route<M, T, R>>,
+}
+
+impl<M, T, R> Gateway<M, T, R>
+where
+    M: RawMutex,
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    pub fn new() -> Self {
+        Self {
+            routes: BTreeMap::new(),
+        }
+    }
+
+    /// Answers requests addressed to `unit_id` from `motor`, at its own slave address.
+    pub fn with_motor(mut self, unit_id: u8, motor: SharedMotor<M, T, R>) -> Self {
+        let route = Route {
+            bus: motor,
+            slave: None,
+        };
+        self.routes.insert(unit_id, route);
+        self
+    }
+
+    /// Answers requests addressed to `unit_id` from `slave` on the port `bus` drives. Map every
+    /// slave on a port through the same `bus`, so requests to them never overlap on the wire.
+    pub fn with_slave(mut self, unit_id: u8, bus: SharedMotor<M, T, R>, slave: u8) -> Self {
+        let route = Route {
+            bus,
+            slave: Some(slave),
+        };
+        self.routes.insert(unit_id, route);
+        self
+    }
+
+    /// Forwards one Modbus TCP ADU and returns the ADU to answer with. Frames that are not
+    /// Modbus TCP at all get no answer.
+    pub async fn handle(&self, adu: &[u8]) -> Option<Vec<u8>> {
+        if adu.len() < MBAP_LEN + 1
+            || adu[2..4] != [0, 0]
+            || usize::from(u16::from_be_bytes([adu[4], adu[5]])) != adu.len() - 6
+        {
+            return None;
+        }
+        let unit_id = adu[6];
+        let pdu = &adu[MBAP_LEN..];
+        let response = match self.forward(unit_id, pdu).await {
+            Ok(response) => response,
+            Err(code) => std::vec![pdu[0] | 0x80, code],
+        };
+        let mut out = Vec::with_capacity(MBAP_LEN + response.len());
+        out.extend_from_slice(&adu[..4]);
+        out.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+        out.push(unit_id);
+        out.extend_from_slice(&response);
+        Some(out)
+    }
+
+    /// Accepts clients on `listener` and serves them concurrently on the calling task.
+    ///
+    /// Only returns if accepting fails.
+    pub async fn serve(&self, listener: TcpListener) -> std::io::Result<Infallible> {
+        let mut clients = FuturesUnordered::new();
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => clients.push(self.serve_client(accepted?.0)),
+                // the client hung up or sent garbage; either way the connection is done
+                Some(_) = clients.next(), if !clients.is_empty() => {}
+            }
+        }
+    }
+
+    async fn serve_client(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut adu = [0u8; 6 + MAX_MBAP_LENGTH];
+        loop {
+            stream.read_exact(&mut adu[..MBAP_LEN]).await?;
+            let length = usize::from(u16::from_be_bytes([adu[4], adu[5]]));
+            if !(2..=MAX_MBAP_LENGTH).contains(&length) {
+                return Err(std::io::ErrorKind::InvalidData.into());
+            }
+            stream.read_exact(&mut adu[MBAP_LEN..6 + length]).await?;
+            if let Some(response) = self.handle(&adu[..6 + length]).await {
+                stream.write_all(&response).await?;
+            }
+        }
+    }
+
+    /// Returns the response PDU, or the exception code to answer with.
+    async fn forward(&self, unit_id: u8, pdu: &[u8]) -> core::result::Result<Vec<u8>, u8> {
+        let route = self.routes.get(&unit_id).ok_or(GATEWAY_PATH_UNAVAILABLE)?;
+        let request = Request::parse(pdu)?;
+        let exchange = async {
+            let mut motor = Addressed::new(route.bus.lock().await, route.slave);
+            match request {
+                Request::ReadHoldings { address, count } => {
+                    let mut values = [0u16; MAX_READ_REGISTERS];
+                    let values = &mut values[..usize::from(count)];
+                    motor.read_holdings_into(address, values).await?;
+                    let mut response = std::vec![0x03, (count * 2) as u8];
+                    values
+                        .iter()
+                        .for_each(|v| response.extend_from_slice(&v.to_be_bytes()));
+                    Ok(response)
+                }
+                Request::WriteHolding { address, value } => {
+                    motor.write_holding(address, value).await?;
+                    Ok(pdu.to_vec())
+                }
+                Request::WriteHoldings { address, values } => {
+                    motor.write_holdings(address, &values).await?;
+                    Ok(pdu[..5].to_vec())
+                }
+            }
+        };
+        exchange.await.map_err(exception_code)
+    }
+}
+
+impl<M, T, R> Default for Gateway<M, T, R>
+where
+    M: RawMutex,
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{NoDelay, ScriptedPort};
+    use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+
+    type TestGateway = Gateway<CriticalSectionRawMutex, ScriptedPort, NoDelay>;
+
+    fn bus(port: ScriptedPort) -> SharedMotor<CriticalSectionRawMutex, ScriptedPort, NoDelay> {
+        Arc::new(Mutex::new(
+            OrcaMotor::new(port).with_response_timeout(NoDelay, 20),
+        ))
+    }
+
+    fn gateway(port: ScriptedPort) -> TestGateway {
+        Gateway::new().with_motor(3, bus(port))
+    }
+
+    #[tokio::test]
+    async fn forwards_register_requests_to_the_mapped_motor() {
+        let gateway = gateway(
+            ScriptedPort::default()
+                .get_holding(1, 317, &[3])
+                .set_holdings(1, 129, &[1, 2]),
+        );
+        assert_eq!(
+            gateway
+                .handle(&[0, 9, 0, 0, 0, 6, 3, 0x03, 0x01, 0x3D, 0x00, 0x01])
+                .await,
+            Some(std::vec![0, 9, 0, 0, 0, 5, 3, 0x03, 0x02, 0x00, 0x03])
+        );
+        assert_eq!(
+            gateway
+                .handle(&[
+                    0, 10, 0, 0, 0, 11, 3, 0x10, 0x00, 0x81, 0x00, 0x02, 0x04, 0x00, 0x01, 0x00,
+                    0x02
+                ])
+                .await,
+            Some(std::vec![
+                0, 10, 0, 0, 0, 6, 3, 0x10, 0x00, 0x81, 0x00, 0x02
+            ])
+        );
+        assert!(gateway.routes[&3].bus.lock().await.port.is_done());
+    }
+
+    #[tokio::test]
+    async fn routes_unit_ids_to_slaves_on_a_shared_bus() {
+        let bus = bus(ScriptedPort::default()
+            .get_holding(2, 317, &[3])
+            .get_holding(5, 317, &[1])
+            .get_holding(1, 317, &[1]));
+        let gateway: TestGateway =
+            Gateway::new()
+                .with_slave(10, bus.clone(), 2)
+                .with_slave(11, bus.clone(), 5);
+        assert_eq!(
+            gateway
+                .handle(&[0, 1, 0, 0, 0, 6, 10, 0x03, 0x01, 0x3D, 0x00, 0x01])
+                .await,
+            Some(std::vec![0, 1, 0, 0, 0, 5, 10, 0x03, 0x02, 0x00, 0x03])
+        );
+        assert_eq!(
+            gateway
+                .handle(&[0, 2, 0, 0, 0, 6, 11, 0x03, 0x01, 0x3D, 0x00, 0x01])
+                .await,
+            Some(std::vec![0, 2, 0, 0, 0, 5, 11, 0x03, 0x02, 0x00, 0x01])
+        );
+        // the application still reaches the bus motor's own slave
+        let mut motor = bus.lock().await;
+        assert_eq!(
+            motor.read_mode().await,
+            Ok(crate::register_map::OrcaModeOfOperation::SleepMode)
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[tokio::test]
+    async fn answers_failures_with_exceptions() {
+        let gateway = gateway(ScriptedPort::default().expect(
+            crate::tests::rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+            std::vec![],
+        ));
+        // no motor behind unit 4
+        assert_eq!(
+            gateway
+                .handle(&[0, 1, 0, 0, 0, 6, 4, 0x03, 0x01, 0x3D, 0x00, 0x01])
+                .await,
+            Some(std::vec![
+                0,
+                1,
+                0,
+                0,
+                0,
+                3,
+                4,
+                0x83,
+                GATEWAY_PATH_UNAVAILABLE
+            ])
+        );
+        assert_eq!(
+            gateway.handle(&[0, 2, 0, 0, 0, 2, 3, 0x2B]).await,
+            Some(std::vec![0, 2, 0, 0, 0, 3, 3, 0xAB, ILLEGAL_FUNCTION])
+        );
+        // the motor never answers
+        assert_eq!(
+            gateway
+                .handle(&[0, 3, 0, 0, 0, 6, 3, 0x03, 0x01, 0x3D, 0x00, 0x01])
+                .await,
+            Some(std::vec![0, 3, 0, 0, 0, 3, 3, 0x83, GATEWAY_TARGET_FAILED])
+        );
+        assert_eq!(gateway.handle(&[0, 4, 0, 1, 0, 2, 3, 0x03]).await, None);
+    }
+}
@@ -1,17 +1,153 @@
 #![no_std]
+#[cfg(feature = "alloc")]
 extern crate alloc;
-use alloc::{vec, vec::Vec};
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+pub mod blocking;
+pub mod control;
+pub mod crc;
+#[cfg(feature = "alloc")]
+pub mod decode;
+mod error;
+#[cfg(feature = "alloc")]
+pub mod estop;
+pub mod event;
+#[cfg(feature = "std")]
+pub mod export;
+pub mod framing;
+#[cfg(feature = "gateway")]
+pub mod gateway;
+#[cfg(feature = "alloc")]
+pub mod listener;
 pub mod pdu_payload;
+pub mod persist;
+#[cfg(feature = "record")]
+pub mod record;
 pub mod register_map;
+#[cfg(feature = "record")]
+pub mod replay;
+pub mod safety;
+pub mod stats;
+pub mod tuning;
+#[cfg(feature = "units")]
+pub mod units;
+pub mod watchdog;
+pub mod zeroing;
+use core::pin::pin;
+use embedded_io_async::Error as _;
 use embedded_registers::Register;
-use rmodbus::{ModbusProto, client::ModbusRequest, guess_response_frame_len};
+use futures_util::future::{Either, select};
+use rmodbus::{
+    client::ModbusRequest, generate_ascii_frame, guess_response_frame_len, parse_ascii_frame,
+};
 
+pub use crate::error::{Error, Result};
+#[cfg(feature = "alloc")]
+use crate::estop::*;
+use crate::event::*;
+use crate::framing::*;
 use crate::pdu_payload::*;
+#[cfg(feature = "record")]
+use crate::record::*;
 use crate::register_map::*;
+use crate::stats::*;
 
-pub struct OrcaMotor<T> {
+/// Holding value of a bit-flag register, using the same byte order as `OrcaErrors`.
+pub(crate) fn flags_value<R: Register>(register: &R) -> u16 {
+    let data = register.data();
+    u16::from_be_bytes([data[0], data[1]])
+}
+
+/// Most holding registers a single Modbus read can return.
+pub const MAX_READ_REGISTERS: usize = 125;
+
+/// Timer of a motor without a response timeout. It has no values, so it can never be waited
+/// on and the motor waits for every response as long as it takes.
+pub enum NoTimeout {}
+
+impl embedded_hal_async::delay::DelayNs for NoTimeout {
+    async fn delay_ns(&mut self, _ns: u32) {
+        match *self {}
+    }
+}
+
+/// Longest a motor may stay silent while a response is due, and the timer that measures it.
+struct ResponseTimeout<R> {
+    timer: R,
+    timeout_ms: u32,
+}
+
+/// Most bytes discarded while waiting for the port to go quiet, so a babbling bus cannot hold
+/// the motor forever.
+const MAX_DRAIN_LEN: usize = 2 * MAX_ASCII_ADU_LEN;
+
+/// Discards whatever `port` receives until it has been silent for `quiet_ms`, or its own read
+/// timeout runs out. Returns the number of bytes discarded.
+pub(crate) async fn drain<P, D>(port: &mut P, delay: &mut D, quiet_ms: u32) -> Result<usize>
+where
+    P: embedded_io_async::Read,
+    D: embedded_hal_async::delay::DelayNs,
+{
+    let mut discarded = 0;
+    let mut scratch = [0u8; 64];
+    while discarded < MAX_DRAIN_LEN {
+        let read = pin!(port.read(&mut scratch));
+        let quiet = pin!(delay.delay_ms(quiet_ms));
+        match select(read, quiet).await {
+            Either::Left((Ok(0), _)) | Either::Right(_) => break,
+            Either::Left((Ok(n), _)) => discarded += n,
+            Either::Left((Err(e), _)) if e.kind() == embedded_io_async::ErrorKind::TimedOut => {
+                break;
+            }
+            Either::Left((Err(e), _)) => return Err(Error::Io(e.kind())),
+        }
+    }
+    Ok(discarded)
+}
+
+/// Fills `buf` from `port` like `read_exact`, counting the bytes read so far in `filled`, so
+/// a read that is cut short still tells how far it got.
+async fn read_counted<P>(port: &mut P, buf: &mut [u8], filled: &mut usize) -> Result<()>
+where
+    P: embedded_io_async::Read,
+{
+    while *filled < buf.len() {
+        match port.read(&mut buf[*filled..]).await {
+            Ok(0) => return Err(Error::UnexpectedEof),
+            Ok(n) => *filled += n,
+            Err(e) => return Err(Error::Io(e.kind())),
+        }
+    }
+    Ok(())
+}
+
+/// What cut a response read short.
+enum Interrupted {
+    EStop,
+    Timeout,
+}
+
+pub struct OrcaMotor<T, R = NoTimeout> {
     pub port: T,
     pub mreq: ModbusRequest,
+    framing: Framing,
+    clock: Option<fn() -> u64>,
+    last_command_us: u64,
+    high_speed: bool,
+    comms_watchdog_ms: Option<u16>,
+    errors: OrcaErrors,
+    errors_since_us: [u64; OrcaErrorFlag::ALL.len()],
+    on_event: Option<OrcaEventHandler>,
+    stats: OrcaStats,
+    bytes_sent: u64,
+    bytes_received: u64,
+    #[cfg(feature = "alloc")]
+    estop: Option<EStopLink>,
+    #[cfg(feature = "record")]
+    recorder: Option<Recorder>,
+    response_timeout: Option<ResponseTimeout<R>>,
 }
 
 impl<T> OrcaMotor<T>
@@ -20,73 +156,620 @@ where
     <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
 {
     pub fn new(port: T) -> Self {
-        Self {
-            port,
-            mreq: ModbusRequest::new(1, ModbusProto::Rtu),
-        }
+        Self::new_with_slave(port, 1)
     }
     pub fn new_with_slave(port: T, slave: u8) -> Self {
         Self {
             port,
-            mreq: ModbusRequest::new(slave, ModbusProto::Rtu),
+            mreq: ModbusRequest::new(slave, Framing::Rtu.proto()),
+            framing: Framing::Rtu,
+            clock: None,
+            last_command_us: 0,
+            high_speed: false,
+            comms_watchdog_ms: None,
+            errors: OrcaErrors::default(),
+            errors_since_us: [0; OrcaErrorFlag::ALL.len()],
+            on_event: None,
+            stats: OrcaStats::default(),
+            bytes_sent: 0,
+            bytes_received: 0,
+            #[cfg(feature = "alloc")]
+            estop: None,
+            #[cfg(feature = "record")]
+            recorder: None,
+            response_timeout: None,
+        }
+    }
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    /// Gives up on a response once the motor has been silent for `timeout_ms`, timed by
+    /// `timer`. The call then fails with [`Error::Timeout`] after the port has been quiet for
+    /// as long again, so a late reply is discarded rather than taken for the answer to the next
+    /// request.
+    pub fn with_response_timeout<D>(self, timer: D, timeout_ms: u32) -> OrcaMotor<T, D> {
+        OrcaMotor {
+            port: self.port,
+            mreq: self.mreq,
+            framing: self.framing,
+            clock: self.clock,
+            last_command_us: self.last_command_us,
+            high_speed: self.high_speed,
+            comms_watchdog_ms: self.comms_watchdog_ms,
+            errors: self.errors,
+            errors_since_us: self.errors_since_us,
+            on_event: self.on_event,
+            stats: self.stats,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_received,
+            #[cfg(feature = "alloc")]
+            estop: self.estop,
+            #[cfg(feature = "record")]
+            recorder: self.recorder,
+            response_timeout: Some(ResponseTimeout { timer, timeout_ms }),
+        }
+    }
+
+    pub fn response_timeout_ms(&self) -> Option<u32> {
+        self.response_timeout.as_ref().map(|t| t.timeout_ms)
+    }
+
+    /// Links the motor to `estop`; see [`EStop`] for what tripping it does. Run
+    /// [`sleep_on_trip`] for a shared motor so it is put to sleep while idle too.
+    #[cfg(feature = "alloc")]
+    pub fn with_estop(mut self, estop: &EStop) -> Self {
+        self.estop = Some(EStopLink {
+            estop: estop.clone(),
+            waker: estop.register(),
+            handled_generation: estop.generation(),
+        });
+        self
+    }
+
+    /// Frames standard register requests with `framing` instead of plain RTU.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.mreq = ModbusRequest::new(self.mreq.unit_id, framing.proto());
+        self.framing = framing;
+        self
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Sets the monotonic microsecond clock used to timestamp commands and events. Without one
+    /// every timestamp is 0 and [`OrcaMotor::keep_alive`] cannot tell how long the motor has
+    /// been idle.
+    pub fn with_clock(mut self, clock: fn() -> u64) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn with_event_handler(mut self, on_event: impl FnMut(&OrcaEvent) + Send + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    pub fn with_event_handler(mut self, on_event: OrcaEventHandler) -> Self {
+        self.on_event = Some(on_event);
+        self
+    }
+
+    /// Logs every request the motor sends from now on to `recorder`, timed by the motor's
+    /// clock.
+    #[cfg(feature = "record")]
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    #[cfg(feature = "record")]
+    pub fn recorder_mut(&mut self) -> Option<&mut Recorder> {
+        self.recorder.as_mut()
+    }
+
+    #[cfg(feature = "record")]
+    fn record(&mut self, started_us: u64, exchange: Exchange, error: Option<&Error>) {
+        let latency_us = self.now_us().saturating_sub(started_us);
+        if let Some(recorder) = self.recorder.as_mut() {
+            let transaction = Transaction {
+                timestamp_us: started_us,
+                slave: self.mreq.unit_id,
+                exchange,
+                latency_us,
+                error: error.map(alloc::string::ToString::to_string),
+                received: Some(core::mem::take(&mut recorder.received)),
+            };
+            // kept by the recorder for take_error
+            let _ = recorder.record(&transaction);
+        }
+    }
+
+    /// Transaction statistics since the motor was created or `reset_stats` was called.
+    pub fn stats(&self) -> &OrcaStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = OrcaStats::default();
+    }
+
+    /// Adds a transaction that started at `started_us`, when the byte counters stood at
+    /// `bytes`, to the statistics.
+    fn track(
+        &mut self,
+        kind: TransactionKind,
+        started_us: u64,
+        bytes: (u64, u64),
+        error: Option<&Error>,
+    ) {
+        let latency_us = self.now_us().saturating_sub(started_us);
+        let bytes = (self.bytes_sent - bytes.0, self.bytes_received - bytes.1);
+        self.stats.record(kind, latency_us, bytes, error);
+    }
+
+    pub fn now_us(&self) -> u64 {
+        self.clock.map_or(0, |clock| clock())
+    }
+
+    /// Time of the last request written to the port, from the motor's clock.
+    pub fn last_command_us(&self) -> u64 {
+        self.last_command_us
+    }
+
+    pub fn is_high_speed(&self) -> bool {
+        self.high_speed
+    }
+
+    pub(crate) fn emit(&mut self, event: OrcaEvent) {
+        if let Some(on_event) = self.on_event.as_mut() {
+            on_event(&event);
         }
     }
 
-    pub async fn read_mode(&mut self) -> anyhow::Result<OrcaModeOfOperation> {
-        let mut bytes = vec![];
-        self.mreq
-            .generate_get_holdings(ModeOfOperation::ADDRESS as u16, 1, &mut bytes)?;
-        self.port.write_all(&bytes).await?;
+    /// Error flags from the latest response or `read_errors`.
+    pub fn errors(&self) -> OrcaErrors {
+        self.errors
+    }
+
+    /// Tracks error flags reported by the motor and raises events on every edge.
+    fn observe_errors(&mut self, errors: OrcaErrors, command: Option<OrcaHighSpeedRequestPDU>) {
+        let slave = self.mreq.unit_id;
+        let timestamp_us = self.now_us();
+        for (i, flag) in OrcaErrorFlag::ALL.into_iter().enumerate() {
+            match (self.errors.contains(flag), errors.contains(flag)) {
+                (false, true) => {
+                    self.errors_since_us[i] = timestamp_us;
+                    self.emit(OrcaEvent::ErrorRaised {
+                        slave,
+                        flag,
+                        timestamp_us,
+                        command,
+                    });
+                    if flag == OrcaErrorFlag::CommunicationTimeout {
+                        self.emit(OrcaEvent::CommsTimeout {
+                            slave,
+                            timestamp_us,
+                        });
+                    }
+                }
+                (true, false) => self.emit(OrcaEvent::ErrorCleared {
+                    slave,
+                    flag,
+                    first_seen_us: self.errors_since_us[i],
+                    timestamp_us,
+                    command,
+                }),
+                _ => {}
+            }
+        }
+        self.errors = errors;
+    }
 
-        let mut buf = [0u8; 3];
-        self.port.read_exact(&mut buf).await?;
+    async fn write_request(&mut self, request: &[u8]) -> Result<()> {
+        self.last_command_us = self.now_us();
+        self.port
+            .write_all(request)
+            .await
+            .map_err(|e| Error::Io(e.kind()))?;
+        self.bytes_sent += request.len() as u64;
+        Ok(())
+    }
+
+    /// Fills `buf` from the port. If `guarded` and the e-stop trips first, gives up and
+    /// returns `false`. Fails with [`Error::Timeout`] once the response timeout runs out.
+    async fn read_response(&mut self, buf: &mut [u8], guarded: bool) -> Result<bool> {
+        let mut filled = 0;
+        let interrupted = {
+            let read = pin!(read_counted(&mut self.port, buf, &mut filled));
+            #[cfg(feature = "alloc")]
+            let tripped = async {
+                match self.estop.as_ref().filter(|_| guarded) {
+                    Some(link) => link.estop.tripped(&link.waker).await,
+                    None => core::future::pending().await,
+                }
+            };
+            // there is no e-stop to race against without `alloc`
+            #[cfg(not(feature = "alloc"))]
+            let tripped = {
+                let _ = guarded;
+                core::future::pending::<()>()
+            };
+            let expired = async {
+                match self.response_timeout.as_mut() {
+                    Some(t) => t.timer.delay_ms(t.timeout_ms).await,
+                    None => core::future::pending().await,
+                }
+            };
+            let interrupt = pin!(async {
+                match select(pin!(tripped), pin!(expired)).await {
+                    Either::Left(_) => Interrupted::EStop,
+                    Either::Right(_) => Interrupted::Timeout,
+                }
+            });
+            match select(read, interrupt).await {
+                Either::Left((read, _)) => {
+                    read?;
+                    None
+                }
+                Either::Right((interrupted, _)) => Some(interrupted),
+            }
+        };
+        self.bytes_received += filled as u64;
+        #[cfg(feature = "record")]
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.received.extend_from_slice(&buf[..filled]);
+        }
+        match interrupted {
+            None => Ok(true),
+            Some(Interrupted::EStop) => Ok(false),
+            Some(Interrupted::Timeout) => {
+                self.drain().await?;
+                Err(Error::Timeout {
+                    operation: "Response",
+                    timeout_ms: self.response_timeout_ms().unwrap_or_default(),
+                })
+            }
+        }
+    }
+
+    /// Discards anything the port receives until it has been quiet for the response timeout.
+    /// Without one there is no telling when the line is quiet, so this does nothing.
+    async fn drain(&mut self) -> Result<()> {
+        if let Some(t) = self.response_timeout.as_mut() {
+            let discarded = drain(&mut self.port, &mut t.timer, t.timeout_ms).await?;
+            self.bytes_received += discarded as u64;
+        }
+        Ok(())
+    }
+
+    /// Request builder for the next standard request. Modbus TCP requests each get a fresh
+    /// transaction id.
+    fn next_request(&mut self) -> &mut ModbusRequest {
+        if self.framing == Framing::Tcp {
+            self.mreq.tr_id = self.mreq.tr_id.wrapping_add(1);
+        }
+        &mut self.mreq
+    }
+
+    /// Writes a standard request generated by rmodbus, hex-encoding it for ASCII framing.
+    async fn write_frame(&mut self, request: &[u8]) -> Result<()> {
+        if self.framing != Framing::Ascii {
+            return self.write_request(request).await;
+        }
+        let mut line = heapless::Vec::<u8, MAX_ASCII_ADU_LEN>::new();
+        generate_ascii_frame(request, &mut line)?;
+        self.write_request(&line).await
+    }
 
-        let mut response = Vec::new();
-        response.extend_from_slice(&buf);
-        let len = guess_response_frame_len(&buf, ModbusProto::Rtu)?;
-        if len > 6 {
-            let mut rest = vec![0u8; (len - 3) as usize];
-            self.port.read_exact(&mut rest).await?;
-            response.extend(rest);
+    /// Reads one standard response, or `None` if a guarded read was cut short by the e-stop.
+    async fn read_frame(&mut self, guarded: bool) -> Result<Option<ModbusFrame>> {
+        let Some(header_len) = self.framing.header_len() else {
+            return self.read_ascii_frame(guarded).await;
+        };
+        let mut response = ModbusFrame::new();
+        response
+            .resize_default(header_len)
+            .map_err(|_| Error::Malformed)?;
+        if !self.read_response(&mut response, guarded).await? {
+            return Ok(None);
         }
+
+        let len = guess_response_frame_len(&response, self.framing.proto())? as usize;
+        if len > response.len() {
+            response.resize_default(len).map_err(|_| Error::Malformed)?;
+            if !self
+                .read_response(&mut response[header_len..], guarded)
+                .await?
+            {
+                return Ok(None);
+            }
+        }
+        Ok(Some(response))
+    }
+
+    /// Reads an ASCII response up to its line feed and decodes it.
+    async fn read_ascii_frame(&mut self, guarded: bool) -> Result<Option<ModbusFrame>> {
+        let mut line = heapless::Vec::<u8, MAX_ASCII_ADU_LEN>::new();
+        loop {
+            let mut byte = [0u8];
+            if !self.read_response(&mut byte, guarded).await? {
+                return Ok(None);
+            }
+            line.push(byte[0]).map_err(|_| Error::Malformed)?;
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        let mut frame = [0u8; MAX_ADU_LEN];
+        let len = parse_ascii_frame(&line, line.len(), &mut frame, 0)? as usize;
+        Ok(Some(
+            ModbusFrame::from_slice(&frame[..len]).map_err(|_| Error::Malformed)?,
+        ))
+    }
+
+    /// Sends a standard request and reads the response, or `None` if a guarded read was cut
+    /// short by the e-stop.
+    async fn exchange(&mut self, request: &[u8], guarded: bool) -> Result<Option<ModbusFrame>> {
+        self.write_frame(request).await?;
+        self.read_frame(guarded).await
+    }
+
+    /// Sends the sleep command without the e-stop guard: `SleepDataStream` in high-speed mode,
+    /// otherwise a `CtrlReg3` write of `SleepMode`.
+    #[cfg(feature = "alloc")]
+    async fn send_sleep_unguarded(&mut self) -> Result<()> {
+        if self.high_speed {
+            self.write_request(&sleep_stream_frame(self.mreq.unit_id))
+                .await?;
+            let sleep =
+                OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::SleepDataStream {});
+            let mut buf = [0u8; MAX_HIGH_SPEED_ADU_LEN];
+            self.read_response(
+                &mut buf[..HIGH_SPEED_ADU_OVERHEAD + sleep.response_len()],
+                false,
+            )
+            .await?;
+        } else {
+            let mut bytes = ModbusFrame::new();
+            self.next_request().generate_set_holding(
+                CtrlReg3::ADDRESS as u16,
+                OrcaModeOfOperation::SleepMode as u16,
+                &mut bytes,
+            )?;
+            self.exchange(&bytes, false).await?;
+        }
+        Ok(())
+    }
+
+    /// Puts the motor to sleep once per trip of its e-stop. Returns the error the interrupted
+    /// call should fail with.
+    #[cfg(feature = "alloc")]
+    async fn engage_estop(&mut self) -> Error {
+        let Some(link) = self.estop.as_mut() else {
+            return Error::EStopped;
+        };
+        let generation = link.estop.generation();
+        if link.handled_generation == generation {
+            return Error::EStopped;
+        }
+        link.handled_generation = generation;
+
+        // the reply to an aborted read is still on its way
+        let sleep_sent = match self.drain().await {
+            Ok(()) => self.send_sleep_unguarded().await.is_ok(),
+            Err(_) => false,
+        };
+        let event = OrcaEvent::EmergencyStop {
+            slave: self.mreq.unit_id,
+            timestamp_us: self.now_us(),
+            sleep_sent,
+        };
+        self.emit(event);
+        Error::EStopped
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    async fn engage_estop(&mut self) -> Error {
+        Error::EStopped
+    }
+
+    #[cfg(feature = "alloc")]
+    fn estop_tripped(&self) -> bool {
+        self.estop.as_ref().is_some_and(|l| l.estop.is_tripped())
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn estop_tripped(&self) -> bool {
+        false
+    }
+
+    async fn transact(&mut self, kind: TransactionKind, request: &[u8]) -> Result<ModbusFrame> {
+        let started_us = self.now_us();
+        #[cfg(feature = "record")]
+        if let Some(recorder) = self.recorder.as_mut() {
+            // bytes read outside a recorded exchange belong to none
+            recorder.received.clear();
+        }
+        let bytes = (self.bytes_sent, self.bytes_received);
+        let result = self.transact_untracked(request).await;
+        self.track(kind, started_us, bytes, result.as_ref().err());
+        #[cfg(feature = "record")]
+        if self.recorder.is_some() {
+            let exchange = Exchange::Standard {
+                framing: self.framing,
+                request: request.to_vec(),
+                response: result.as_ref().ok().map(|r| r.to_vec()),
+            };
+            self.record(started_us, exchange, result.as_ref().err());
+        }
+        result
+    }
+
+    async fn transact_untracked(&mut self, request: &[u8]) -> Result<ModbusFrame> {
+        if self.estop_tripped() {
+            return Err(self.engage_estop().await);
+        }
+        let Some(response) = self.exchange(request, true).await? else {
+            return Err(self.engage_estop().await);
+        };
         // check if frame has no Modbus error inside
         self.mreq.parse_ok(&response)?;
 
-        let mut data = Vec::new();
+        Ok(response)
+    }
+
+    /// Fills `values` from consecutive holding registers starting at `address`.
+    pub async fn read_holdings_into(&mut self, address: u16, values: &mut [u16]) -> Result<()> {
+        let mut bytes = ModbusFrame::new();
+        self.next_request()
+            .generate_get_holdings(address, values.len() as u16, &mut bytes)?;
+        let response = self
+            .transact(TransactionKind::ReadRegisters, &bytes)
+            .await?;
+
+        let mut data = heapless::Vec::<u16, MAX_READ_REGISTERS>::new();
         self.mreq.parse_u16(&response, &mut data)?;
+        if data.len() != values.len() {
+            return Err(Error::RegisterCount {
+                expected: values.len() as u16,
+                got: data.len() as u16,
+            });
+        }
+        values.copy_from_slice(&data);
+        Ok(())
+    }
 
-        Ok(OrcaModeOfOperation::try_from(data[0] as u8)?)
+    /// Reads `N` consecutive holding registers starting at `address`.
+    pub async fn read_holdings<const N: usize>(&mut self, address: u16) -> Result<[u16; N]> {
+        let mut values = [0; N];
+        self.read_holdings_into(address, &mut values).await?;
+        Ok(values)
     }
 
-    pub async fn set_mode(&mut self, mode: OrcaModeOfOperation) -> anyhow::Result<()> {
-        let mut bytes = vec![];
-        self.mreq
-            .generate_set_holding(CtrlReg3::ADDRESS as u16, mode as u16, &mut bytes)?;
-        self.port.write_all(&bytes).await?;
+    pub async fn read_holding(&mut self, address: u16) -> Result<u16> {
+        let [value] = self.read_holdings(address).await?;
+        Ok(value)
+    }
 
-        let mut buf = [0u8; 8];
-        self.port.read_exact(&mut buf).await?;
+    /// Reads a 32-bit value stored as a low/high register pair starting at `address`.
+    pub async fn read_holding_u32(&mut self, address: u16) -> Result<u32> {
+        let [low, high] = self.read_holdings(address).await?;
+        Ok((high as u32) << 16 | low as u32)
+    }
 
-        // check if frame has no Modbus error inside
-        self.mreq.parse_ok(&buf)?;
+    pub async fn write_holding(&mut self, address: u16, value: u16) -> Result<()> {
+        let mut bytes = ModbusFrame::new();
+        self.next_request()
+            .generate_set_holding(address, value, &mut bytes)?;
+        self.transact(TransactionKind::WriteRegisters, &bytes)
+            .await?;
+        Ok(())
+    }
 
+    pub async fn write_holdings(&mut self, address: u16, values: &[u16]) -> Result<()> {
+        let mut bytes = ModbusFrame::new();
+        self.next_request()
+            .generate_set_holdings_bulk(address, values, &mut bytes)?;
+        self.transact(TransactionKind::WriteRegisters, &bytes)
+            .await?;
         Ok(())
     }
 
+    pub async fn read_mode(&mut self) -> Result<OrcaModeOfOperation> {
+        let mode = self.read_holding(ModeOfOperation::ADDRESS as u16).await? as u8;
+        OrcaModeOfOperation::try_from(mode).map_err(|_| Error::UnknownMode(mode))
+    }
+
+    pub async fn set_mode(&mut self, mode: OrcaModeOfOperation) -> Result<()> {
+        self.write_holding(CtrlReg3::ADDRESS as u16, mode as u16)
+            .await
+    }
+
+    pub async fn read_errors(&mut self) -> Result<OrcaErrors> {
+        let errors = OrcaErrors::from(self.read_holding(Error0::ADDRESS as u16).await?);
+        self.observe_errors(errors, None);
+        Ok(errors)
+    }
+
     pub async fn send_high_speed_adu(
         &mut self,
         adu: &OrcaHighSpeedRequestADU,
-    ) -> anyhow::Result<OrcaHighSpeedResponsePDU> {
-        let adu_vec = adu.to_vec();
-        self.port.write_all(&adu_vec).await?;
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.exchange_high_speed(adu.slave_address, adu.pdu).await
+    }
 
-        let mut buf = vec![0u8; adu.num_response_bytes()];
-        self.port.read_exact(&mut buf).await?;
+    /// Sends `pdu` to this motor. Unlike building an [`OrcaHighSpeedRequestADU`] first, this
+    /// computes the frame's CRC only once.
+    pub async fn send_high_speed(
+        &mut self,
+        pdu: OrcaHighSpeedRequestPDU,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.exchange_high_speed(self.mreq.unit_id, pdu).await
+    }
 
-        let response_adu = OrcaHighSpeedResponseADU::from_bytes(&buf)?;
+    async fn exchange_high_speed(
+        &mut self,
+        slave: u8,
+        pdu: OrcaHighSpeedRequestPDU,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        let started_us = self.now_us();
+        #[cfg(feature = "record")]
+        if let Some(recorder) = self.recorder.as_mut() {
+            // bytes read outside a recorded exchange belong to none
+            recorder.received.clear();
+        }
+        let bytes = (self.bytes_sent, self.bytes_received);
+        let result = self.exchange_high_speed_untracked(slave, pdu).await;
+        let kind = TransactionKind::of_high_speed(&pdu);
+        self.track(kind, started_us, bytes, result.as_ref().err());
+        #[cfg(feature = "record")]
+        if self.recorder.is_some() {
+            let exchange = Exchange::HighSpeed {
+                request: pdu,
+                response: result.as_ref().ok().copied(),
+            };
+            self.record(started_us, exchange, result.as_ref().err());
+        }
+        result
+    }
 
-        if adu.slave_address != response_adu.slave_address {
-            anyhow::bail!("Slave address mismatch");
+    async fn exchange_high_speed_untracked(
+        &mut self,
+        slave: u8,
+        pdu: OrcaHighSpeedRequestPDU,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        if !self.framing.carries_high_speed() {
+            return Err(Error::Unsupported);
+        }
+        if self.estop_tripped() {
+            return Err(self.engage_estop().await);
+        }
+        self.write_request(&pdu.to_frame(slave)).await?;
+
+        let mut buf = [0u8; MAX_HIGH_SPEED_ADU_LEN];
+        let buf = &mut buf[..HIGH_SPEED_ADU_OVERHEAD + pdu.response_len()];
+        if !self.read_response(buf, true).await? {
+            return Err(self.engage_estop().await);
+        }
+
+        let response_adu = OrcaHighSpeedResponseADU::from_bytes(buf)?;
+
+        if slave != response_adu.slave_address {
+            return Err(Error::SlaveMismatch {
+                expected: slave,
+                got: response_adu.slave_address,
+            });
+        }
+        if let Some(response) = response_adu.pdu.command_response() {
+            self.observe_errors(response.error, Some(pdu));
         }
 
         Ok(response_adu.pdu)
@@ -95,38 +778,577 @@ where
         &mut self,
         baud_rate: u32,
         delay_us: u16,
-    ) -> anyhow::Result<OrcaHighSpeedResponsePDU> {
-        self.send_high_speed_adu(&OrcaHighSpeedRequestADU::new(
-            self.mreq.unit_id,
-            OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload {
-                sub_function_code: ManageHighSpeedRequestSubFunctionCode::Enable,
-                baud_rate,
-                delay_us,
-            }),
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        let response = self
+            .send_high_speed(OrcaHighSpeedRequestPDU::Manage(
+                ManageHighSpeedRequestPDUPayload {
+                    sub_function_code: ManageHighSpeedRequestSubFunctionCode::Enable,
+                    baud_rate,
+                    delay_us,
+                },
+            ))
+            .await?;
+        self.high_speed = true;
+        Ok(response)
+    }
+    pub async fn disable_high_speed(&mut self) -> Result<OrcaHighSpeedResponsePDU> {
+        let response = self
+            .send_high_speed(OrcaHighSpeedRequestPDU::Manage(
+                ManageHighSpeedRequestPDUPayload {
+                    sub_function_code: ManageHighSpeedRequestSubFunctionCode::Disable,
+                    ..Default::default()
+                },
+            ))
+            .await?;
+        self.high_speed = false;
+        Ok(response)
+    }
+
+    pub async fn send_position_high_speed(
+        &mut self,
+        position_um: i32,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_high_speed(OrcaHighSpeedRequestPDU::Command(
+            MotorCommandRequestPDUPayload::PositionControlStream { position_um },
         ))
         .await
     }
-    pub async fn disable_high_speed(&mut self) -> anyhow::Result<OrcaHighSpeedResponsePDU> {
-        self.send_high_speed_adu(&OrcaHighSpeedRequestADU::new(
-            self.mreq.unit_id,
-            OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload {
-                sub_function_code: ManageHighSpeedRequestSubFunctionCode::Disable,
-                ..Default::default()
-            }),
+
+    pub async fn send_force_high_speed(
+        &mut self,
+        force_mn: i32,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_high_speed(OrcaHighSpeedRequestPDU::Command(
+            MotorCommandRequestPDUPayload::ForceControlStream { force_mn },
         ))
         .await
     }
 
-    pub async fn send_position_high_speed(
+    pub async fn send_read_high_speed(
         &mut self,
-        position_um: i32,
-    ) -> anyhow::Result<OrcaHighSpeedResponsePDU> {
-        self.send_high_speed_adu(&OrcaHighSpeedRequestADU::new(
-            self.mreq.unit_id,
-            OrcaHighSpeedRequestPDU::Command(
-                MotorCommandRequestPDUPayload::PositionControlStream { position_um },
-            ),
-        ))
+        register_address: u16,
+        register_width: u8,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_high_speed(OrcaHighSpeedRequestPDU::Read(MotorReadRequestPDUPayload {
+            register_address,
+            register_width,
+        }))
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::*;
+    use crate::tuning::*;
+    use crate::zeroing::*;
+    use futures::executor::block_on;
+    use std::collections::VecDeque;
+    use std::{vec, vec::Vec};
+
+    /// Appends the Modbus RTU CRC to `frame`.
+    pub(crate) fn rtu(frame: &[u8]) -> Vec<u8> {
+        let crc = crate::crc::checksum(frame);
+        let mut out = frame.to_vec();
+        out.extend_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Port that checks each request against a script and answers with the scripted reply.
+    #[derive(Default)]
+    pub(crate) struct ScriptedPort {
+        script: VecDeque<(Vec<u8>, Vec<u8>)>,
+        pending: VecDeque<u8>,
+    }
+
+    impl ScriptedPort {
+        pub(crate) fn expect(mut self, request: Vec<u8>, response: Vec<u8>) -> Self {
+            self.script.push_back((request, response));
+            self
+        }
+        pub(crate) fn get_holding(self, slave: u8, address: u16, values: &[u16]) -> Self {
+            let [ah, al] = address.to_be_bytes();
+            let mut response = vec![slave, 0x03, (values.len() * 2) as u8];
+            values
+                .iter()
+                .for_each(|v| response.extend_from_slice(&v.to_be_bytes()));
+            self.expect(
+                rtu(&[slave, 0x03, ah, al, 0x00, values.len() as u8]),
+                rtu(&response),
+            )
+        }
+        pub(crate) fn set_holding(self, slave: u8, address: u16, value: u16) -> Self {
+            let [ah, al] = address.to_be_bytes();
+            let [vh, vl] = value.to_be_bytes();
+            let frame = rtu(&[slave, 0x06, ah, al, vh, vl]);
+            self.expect(frame.clone(), frame)
+        }
+        pub(crate) fn set_holdings(self, slave: u8, address: u16, values: &[u16]) -> Self {
+            let [ah, al] = address.to_be_bytes();
+            let mut request = vec![slave, 0x10, ah, al, 0x00, values.len() as u8];
+            request.push((values.len() * 2) as u8);
+            values
+                .iter()
+                .for_each(|v| request.extend_from_slice(&v.to_be_bytes()));
+            self.expect(
+                rtu(&request),
+                rtu(&[slave, 0x10, ah, al, 0x00, values.len() as u8]),
+            )
+        }
+        pub(crate) fn is_done(&self) -> bool {
+            self.script.is_empty() && self.pending.is_empty()
+        }
+    }
+
+    impl embedded_io_async::ErrorType for ScriptedPort {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_io_async::Write for ScriptedPort {
+        async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+            let (request, response) = self.script.pop_front().expect("unexpected request");
+            assert_eq!(buf, &request[..]);
+            self.pending.extend(response);
+            Ok(buf.len())
+        }
+        async fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl embedded_io_async::Read for ScriptedPort {
+        async fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            if self.pending.is_empty() {
+                // the motor never answers
+                core::future::pending::<()>().await;
+            }
+            let n = buf.len().min(self.pending.len());
+            buf.iter_mut()
+                .zip(self.pending.drain(..n))
+                .for_each(|(b, p)| *b = p);
+            Ok(n)
+        }
+    }
+
+    impl embedded_io::Write for ScriptedPort {
+        fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+            block_on(embedded_io_async::Write::write(self, buf))
+        }
+        fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    impl embedded_io::Read for ScriptedPort {
+        fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            assert!(!self.pending.is_empty(), "the motor never answers");
+            block_on(embedded_io_async::Read::read(self, buf))
+        }
+    }
+
+    pub(crate) struct NoDelay;
+
+    impl embedded_hal_async::delay::DelayNs for NoDelay {
+        async fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    impl embedded_hal::delay::DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn read_mode_frame() {
+        let port = ScriptedPort::default().get_holding(1, 317, &[3]);
+        let mut motor = OrcaMotor::new(port);
+        let mode = block_on(motor.read_mode()).unwrap();
+        assert_eq!(mode, OrcaModeOfOperation::PositionMode);
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn modbus_exception_is_an_error() {
+        let port = ScriptedPort::default().expect(
+            rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+            rtu(&[1, 0x83, 0x02]),
+        );
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(
+            block_on(motor.read_mode()),
+            Err(Error::Modbus(rmodbus::ErrorKind::IllegalDataAddress))
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn auto_zero_reports_failure() {
+        let params = AutoZeroParams {
+            zero_mode: OrcaZeroMode::AutoZeroEnabled,
+            force_n: 30,
+            speed_mmps: 50,
+            exit_mode: OrcaAutoZeroExitMode::PositionMode,
+        };
+        let failed = OrcaErrors {
+            auto_zero_failed: true,
+            ..Default::default()
+        };
+        let port = ScriptedPort::default()
+            .set_holding(1, 171, 2)
+            .set_holding(1, 172, 30)
+            .set_holding(1, 177, 50)
+            .set_holding(1, 173, 3)
+            .set_holding(1, 3, 55)
+            .get_holding(1, 317, &[55])
+            .get_holding(1, 342, &[100, 0])
+            .get_holding(1, 317, &[1])
+            .get_holding(1, 342, &[0, 0])
+            .get_holding(1, 432, &[failed.into()]);
+        let mut motor = OrcaMotor::new(port);
+        let mut polls = vec![];
+        let outcome =
+            block_on(motor.auto_zero(params, &mut NoDelay, 1000, |p| polls.push(p))).unwrap();
+        assert_eq!(outcome, AutoZeroOutcome::Failed { errors: failed });
+        assert_eq!(polls.len(), 2);
+        assert_eq!(polls[0].position_um, 100);
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn clear_errors_reports_remaining() {
+        let remaining = OrcaErrors {
+            voltage_invalid: true,
+            ..Default::default()
+        };
+        let port = ScriptedPort::default().set_holding(1, 0, 0b10).get_holding(
+            1,
+            432,
+            &[remaining.into()],
+        );
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(block_on(motor.clear_errors()).unwrap(), remaining);
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn save_waits_for_flash() {
+        let mut reg = CtrlReg2::default();
+        reg.write_tuning_save(true);
+        reg.write_haptic_config_save(true);
+        let mut groups = SaveGroups::default();
+        groups.tuning = true;
+        groups.haptic_config = true;
+        assert_eq!(u16::from(groups), flags_value(&reg));
+
+        let gains = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+        let port = ScriptedPort::default()
+            .get_holding(1, 129, &gains)
+            .set_holding(1, 2, flags_value(&reg))
+            .get_holding(1, 2, &[flags_value(&reg)])
+            .get_holding(1, 2, &[0])
+            .get_holding(1, 129, &gains);
+        let mut motor = OrcaMotor::new(port);
+        block_on(motor.save(groups, &mut NoDelay, 1000)).unwrap();
+        assert!(motor.port.is_done());
+
+        let mut changed = gains;
+        changed[5] = 0;
+        let port = ScriptedPort::default()
+            .get_holding(1, 129, &gains)
+            .set_holding(1, 2, flags_value(&reg))
+            .get_holding(1, 2, &[0])
+            .get_holding(1, 129, &changed);
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(
+            block_on(motor.save(groups, &mut NoDelay, 1000)),
+            Err(Error::ReadBack {
+                address: 134,
+                expected: 60,
+                got: 0
+            })
+        );
+    }
+
+    #[test]
+    fn position_inversion_sets_pos_sign() {
+        let port = ScriptedPort::default()
+            .set_holding(1, 152, 1)
+            .set_holding(1, 152, 0)
+            .get_holding(1, 152, &[0]);
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(block_on(motor.set_position_inverted(true)), Ok(()));
+        assert_eq!(block_on(motor.set_position_inverted(false)), Ok(()));
+        assert_eq!(block_on(motor.position_inverted()), Ok(false));
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn soft_reset_bounds_every_poll() {
+        let mut reg = CtrlReg0::default();
+        reg.write_reset(true);
+        let reset = rtu(&[1, 0x06, 0x00, 0x00, 0x00, flags_value(&reg) as u8]);
+        let poll = rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]);
+
+        let port = ScriptedPort::default()
+            .expect(reset.clone(), vec![])
+            .expect(poll.clone(), vec![])
+            .get_holding(1, 317, &[1]);
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(
+            block_on(motor.soft_reset(&mut NoDelay, 1000)),
+            Ok(OrcaModeOfOperation::SleepMode)
+        );
+        assert!(motor.port.is_done());
+
+        let port = ScriptedPort::default()
+            .expect(reset, vec![])
+            .expect(poll.clone(), vec![])
+            .expect(poll, vec![]);
+        let mut motor = OrcaMotor::new(port);
+        assert_eq!(
+            block_on(motor.soft_reset(&mut NoDelay, 400)),
+            Err(Error::Timeout {
+                operation: "Reset",
+                timeout_ms: 400
+            })
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn response_timeout_fails_and_resyncs() {
+        let port = ScriptedPort::default()
+            .expect(rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]), vec![1, 0x03])
+            .get_holding(1, 317, &[3]);
+        let mut motor = OrcaMotor::new(port).with_response_timeout(NoDelay, 10);
+        assert_eq!(
+            block_on(motor.read_mode()),
+            Err(Error::Timeout {
+                operation: "Response",
+                timeout_ms: 10
+            })
+        );
+        assert_eq!(
+            block_on(motor.read_mode()),
+            Ok(OrcaModeOfOperation::PositionMode)
+        );
+        let read = motor.stats().get(TransactionKind::ReadRegisters);
+        assert_eq!((read.count, read.errors.timeout), (2, 1));
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn position_gains_round_trip() {
+        let gains = PositionGains {
+            p_gain: 10,
+            i_gain: 20,
+            dv_gain: 30,
+            de_gain: 40,
+            force_saturation_mn: 0x0001_86A0,
+        };
+        let registers = [10, 20, 30, 40, 0x86A0, 0x0001];
+        let port = ScriptedPort::default()
+            .set_holdings(1, 133, &registers)
+            .set_holding(1, 1, 1 << 10)
+            .get_holding(1, 133, &registers);
+        let mut motor = OrcaMotor::new(port);
+        block_on(motor.set_position_gains(gains)).unwrap();
+        assert_eq!(block_on(motor.position_gains()).unwrap(), gains);
+        assert!(motor.port.is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn comms_timeout_raises_one_event_and_keepalive_feeds_watchdog() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex};
+        static NOW_US: AtomicU64 = AtomicU64::new(0);
+
+        let timed_out = OrcaErrors {
+            communication_timeout: true,
+            ..Default::default()
+        };
+        let port = ScriptedPort::default()
+            .set_holding(1, 163, 100)
+            .get_holding(1, 432, &[timed_out.into()])
+            .get_holding(1, 432, &[timed_out.into()])
+            .get_holding(1, 317, &[1]);
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        let mut motor = OrcaMotor::new(port)
+            .with_clock(|| NOW_US.load(Ordering::Relaxed))
+            .with_event_handler(move |e| sink.lock().unwrap().push(*e));
+
+        block_on(motor.arm_comms_watchdog(100)).unwrap();
+        block_on(motor.read_errors()).unwrap();
+        block_on(motor.read_errors()).unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                OrcaEvent::ErrorRaised {
+                    slave: 1,
+                    flag: OrcaErrorFlag::CommunicationTimeout,
+                    timestamp_us: 0,
+                    command: None,
+                },
+                OrcaEvent::CommsTimeout {
+                    slave: 1,
+                    timestamp_us: 0
+                }
+            ]
+        );
+
+        NOW_US.store(50_000, Ordering::Relaxed);
+        assert!(!block_on(motor.keep_alive(20)).unwrap());
+        NOW_US.store(80_000, Ordering::Relaxed);
+        assert!(block_on(motor.keep_alive(20)).unwrap());
+        assert_eq!(motor.last_command_us(), 80_000);
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn keepalive_without_clock_sends_every_call() {
+        let port = ScriptedPort::default()
+            .set_holding(1, 163, 100)
+            .get_holding(1, 317, &[1])
+            .get_holding(1, 317, &[1]);
+        let mut motor = OrcaMotor::new(port);
+        block_on(motor.arm_comms_watchdog(100)).unwrap();
+        assert!(block_on(motor.keep_alive(20)).unwrap());
+        assert!(block_on(motor.keep_alive(20)).unwrap());
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn keepalive_task_feeds_motors_with_a_response_timeout() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::mutex::Mutex;
+
+        let port = ScriptedPort::default()
+            .set_holding(1, 163, 10)
+            .get_holding(1, 317, &[1])
+            .expect(rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]), vec![]);
+        let motor = OrcaMotor::new(port).with_response_timeout(NoDelay, 20);
+        let motor = Mutex::<NoopRawMutex, _>::new(motor);
+        block_on(async { motor.lock().await.arm_comms_watchdog(10).await }).unwrap();
+        // the second keepalive finds the motor silent
+        assert!(matches!(
+            block_on(crate::watchdog::keepalive_task(&motor, &mut NoDelay, 20)),
+            Err(Error::Timeout { .. })
+        ));
+        assert!(block_on(motor.lock()).port.is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn tripped_estop_blocks_soft_reset() {
+        let estop = EStop::new();
+        let port = ScriptedPort::default().set_holding(1, 3, 1);
+        let mut motor = OrcaMotor::new(port).with_estop(&estop);
+        estop.trip();
+        assert_eq!(
+            block_on(motor.soft_reset(&mut NoDelay, 1000)),
+            Err(Error::EStopped)
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn estop_preempts_pending_read() {
+        let estop = EStop::new();
+        let port = ScriptedPort::default()
+            .expect(rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]), vec![])
+            .set_holding(1, 3, 1)
+            .get_holding(1, 317, &[1]);
+        let mut motor = OrcaMotor::new(port).with_estop(&estop);
+
+        let (mode, ()) = block_on(futures::future::join(motor.read_mode(), async {
+            estop.trip()
+        }));
+        assert_eq!(mode, Err(Error::EStopped));
+        assert_eq!(block_on(motor.read_mode()), Err(Error::EStopped));
+
+        estop.rearm();
+        assert_eq!(
+            block_on(motor.read_mode()).unwrap(),
+            OrcaModeOfOperation::SleepMode
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn estop_puts_idle_motor_to_sleep() {
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::mutex::Mutex;
+        use futures::future::{Either, select};
+        use std::sync::Arc;
+
+        let estop = EStop::new();
+        let port = ScriptedPort::default().set_holding(1, 3, 1);
+        let events = Arc::new(std::sync::Mutex::new(vec![]));
+        let sink = events.clone();
+        let motor = Mutex::<NoopRawMutex, _>::new(
+            OrcaMotor::new(port)
+                .with_estop(&estop)
+                .with_event_handler(move |e| sink.lock().unwrap().push(*e)),
+        );
+
+        let task = pin!(sleep_on_trip(&motor));
+        let trip = pin!(async {
+            estop.trip();
+            futures::pending!();
+            estop.trip();
+            futures::pending!();
+        });
+        assert!(matches!(block_on(select(task, trip)), Either::Right(_)));
+        assert_eq!(
+            *events.lock().unwrap(),
+            [OrcaEvent::EmergencyStop {
+                slave: 1,
+                timestamp_us: 0,
+                sleep_sent: true
+            }]
+        );
+        let mut motor = motor.try_lock().unwrap();
+        assert!(motor.port.is_done());
+        assert_eq!(block_on(motor.read_mode()), Err(Error::EStopped));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn error_edges_carry_first_seen_time() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex};
+        static NOW_US: AtomicU64 = AtomicU64::new(0);
+
+        let clipping = OrcaErrors {
+            force_clipping: true,
+            ..Default::default()
+        };
+        let port = ScriptedPort::default()
+            .get_holding(1, 432, &[clipping.into()])
+            .get_holding(1, 432, &[0]);
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = events.clone();
+        let mut motor = OrcaMotor::new(port)
+            .with_clock(|| NOW_US.load(Ordering::Relaxed))
+            .with_event_handler(move |e| sink.lock().unwrap().push(*e));
+
+        NOW_US.store(10, Ordering::Relaxed);
+        block_on(motor.read_errors()).unwrap();
+        NOW_US.store(25, Ordering::Relaxed);
+        block_on(motor.read_errors()).unwrap();
+        assert_eq!(
+            events.lock().unwrap()[1],
+            OrcaEvent::ErrorCleared {
+                slave: 1,
+                flag: OrcaErrorFlag::ForceClipping,
+                first_seen_us: 10,
+                timestamp_us: 25,
+                command: None,
+            }
+        );
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,338 @@
+//! Passive monitoring of a bus driven by another master, such as a PLC.
+//!
+//! [`OrcaListener`] only ever reads its port. It cuts the byte stream into frames with the
+//! [`crate::decode`] framing, pairs each request with the response that follows it, and
+//! reports what happened as [`ListenerEvent`]s.
+
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+
+use crate::decode::*;
+use crate::pdu_payload::*;
+use crate::register_map::OrcaModeOfOperation;
+use crate::{Error, Result};
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum ListenerEvent {
+    /// The master sent a high-speed command stream frame.
+    SetpointSent {
+        slave: u8,
+        command: MotorCommandRequestPDUPayload,
+    },
+    /// A motor answered a high-speed frame. `mode` is only carried by read and write responses.
+    TelemetryReturned {
+        slave: u8,
+        mode: Option<OrcaModeOfOperation>,
+        telemetry: MotorCommandResponsePDUPayload,
+    },
+    /// A motor returned registers starting at `address`, through a standard or high-speed read.
+    RegistersRead {
+        slave: u8,
+        address: u16,
+        values: Vec<u16>,
+    },
+    /// A motor confirmed a write of `values` starting at `address`.
+    RegisterWritten {
+        slave: u8,
+        address: u16,
+        values: Vec<u16>,
+    },
+    /// A motor acknowledged a high-speed manage frame.
+    HighSpeedChanged {
+        slave: u8,
+        enabled: bool,
+        baud_rate: u32,
+        delay_us: u16,
+    },
+    /// A motor answered `function` with a Modbus exception.
+    Exception { slave: u8, function: u8, code: u8 },
+    /// `request` got no response before the next request went out.
+    Unanswered { request: DecodedFrame },
+    /// `response` did not match the outstanding request, if there was one.
+    Unpaired { response: DecodedFrame },
+    /// `skipped` bytes fit no frame and were dropped to get back in sync.
+    Resynced { skipped: usize },
+}
+
+/// Listens to a bus without ever transmitting.
+///
+/// Only the functions [`crate::decode`] knows are framed; anything else is skipped and
+/// reported through [`ListenerEvent::Resynced`].
+pub struct OrcaListener<T> {
+    port: T,
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    skipped: usize,
+    pending: Option<DecodedFrame>,
+    events: VecDeque<ListenerEvent>,
+}
+
+impl<T: embedded_io_async::Read> OrcaListener<T> {
+    pub fn new(port: T) -> Self {
+        Self {
+            port,
+            buf: [0; MAX_FRAME_LEN],
+            len: 0,
+            skipped: 0,
+            pending: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn into_port(self) -> T {
+        self.port
+    }
+
+    /// Waits for the next event on the bus.
+    pub async fn next_event(&mut self) -> Result<ListenerEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+            match self.take_frame() {
+                Some(len) => {
+                    if self.skipped > 0 {
+                        self.events.push_back(ListenerEvent::Resynced {
+                            skipped: core::mem::take(&mut self.skipped),
+                        });
+                    }
+                    let prefer = match self.pending {
+                        Some(_) => Direction::Response,
+                        None => Direction::Request,
+                    };
+                    // take_frame only returns CRC-valid frames of a known length
+                    if let Ok(frame) = decode_frame(&self.buf[..len], prefer) {
+                        self.pair(frame);
+                    }
+                    self.consume(len);
+                }
+                None => {
+                    let n = self
+                        .port
+                        .read(&mut self.buf[self.len..])
+                        .await
+                        .map_err(|e| Error::Io(embedded_io_async::Error::kind(&e)))?;
+                    if n == 0 {
+                        return Err(Error::UnexpectedEof);
+                    }
+                    self.len += n;
+                }
+            }
+        }
+    }
+
+    /// Length of the frame at the front of the buffer, dropping bytes until one starts there.
+    /// `None` means more bytes are needed.
+    fn take_frame(&mut self) -> Option<usize> {
+        loop {
+            let buf = &self.buf[..self.len];
+            let mut lens: Vec<usize> = candidate_lens(buf)
+                .into_iter()
+                .flatten()
+                .filter(|len| *len <= MAX_FRAME_LEN)
+                .collect();
+            lens.sort_unstable();
+            if let Some(len) = first_valid_len(buf, lens.iter().copied()) {
+                return Some(len);
+            }
+            // a frame that is still arriving, or a header too short to tell
+            if buf.len() < 3 || lens.iter().any(|len| *len > buf.len()) {
+                return None;
+            }
+            self.consume(1);
+            self.skipped += 1;
+        }
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.buf.copy_within(n..self.len, 0);
+        self.len -= n;
+    }
+
+    fn pair(&mut self, frame: DecodedFrame) {
+        let slave = frame.slave;
+        if frame.direction == Direction::Request {
+            if let Some(request) = self.pending.take() {
+                self.events.push_back(ListenerEvent::Unanswered { request });
+            }
+            if let DecodedPdu::HighSpeedRequest(OrcaHighSpeedRequestPDU::Command(command)) =
+                frame.pdu
+            {
+                self.events
+                    .push_back(ListenerEvent::SetpointSent { slave, command });
+            }
+            self.pending = Some(frame);
+            return;
+        }
+
+        let request = self.pending.take().filter(|r| r.slave == slave);
+        let request = request.as_ref().map(|r| &r.pdu);
+        let event = match (&frame.pdu, request) {
+            (DecodedPdu::HighSpeedResponse(OrcaHighSpeedResponsePDU::Manage(p)), _) => {
+                ListenerEvent::HighSpeedChanged {
+                    slave,
+                    enabled: p.state_command == ManageHighSpeedRequestSubFunctionCode::Enable,
+                    baud_rate: p.baud_rate,
+                    delay_us: p.delay_us,
+                }
+            }
+            (DecodedPdu::HighSpeedResponse(response), request) => {
+                let telemetry = *response.command_response().expect("not a manage response");
+                let (mode, register) = match (response, request) {
+                    (
+                        OrcaHighSpeedResponsePDU::Read(p),
+                        Some(DecodedPdu::HighSpeedRequest(OrcaHighSpeedRequestPDU::Read(r))),
+                    ) => {
+                        let values = register_values(p.read_register_value, r.register_width);
+                        let event = ListenerEvent::RegistersRead {
+                            slave,
+                            address: r.register_address,
+                            values,
+                        };
+                        (Some(p.mode_of_operation), Some(event))
+                    }
+                    (
+                        OrcaHighSpeedResponsePDU::Write(p),
+                        Some(DecodedPdu::HighSpeedRequest(OrcaHighSpeedRequestPDU::Write(r))),
+                    ) => {
+                        let values = register_values(r.register_data, r.register_width);
+                        let event = ListenerEvent::RegisterWritten {
+                            slave,
+                            address: r.register_address,
+                            values,
+                        };
+                        (Some(p.mode_of_operation), Some(event))
+                    }
+                    (OrcaHighSpeedResponsePDU::Read(p), _) => (Some(p.mode_of_operation), None),
+                    (OrcaHighSpeedResponsePDU::Write(p), _) => (Some(p.mode_of_operation), None),
+                    _ => (None, None),
+                };
+                self.events.extend(register);
+                ListenerEvent::TelemetryReturned {
+                    slave,
+                    mode,
+                    telemetry,
+                }
+            }
+            (
+                DecodedPdu::ReadHoldingsResponse { values },
+                Some(DecodedPdu::ReadHoldings { address, count }),
+            ) if values.len() == usize::from(*count) => ListenerEvent::RegistersRead {
+                slave,
+                address: *address,
+                values: values.clone(),
+            },
+            (DecodedPdu::WriteHolding { address, value }, _) => ListenerEvent::RegisterWritten {
+                slave,
+                address: *address,
+                values: vec![*value],
+            },
+            (
+                DecodedPdu::WriteHoldingsResponse { address, count },
+                Some(DecodedPdu::WriteHoldings {
+                    address: requested,
+                    values,
+                }),
+            ) if address == requested && usize::from(*count) == values.len() => {
+                ListenerEvent::RegisterWritten {
+                    slave,
+                    address: *address,
+                    values: values.clone(),
+                }
+            }
+            (DecodedPdu::Exception { function, code }, _) => ListenerEvent::Exception {
+                slave,
+                function: *function,
+                code: *code,
+            },
+            _ => ListenerEvent::Unpaired { response: frame },
+        };
+        self.events.push_back(event);
+    }
+}
+
+/// Registers of a high-speed read or write, low word first as in `read_holding_u32`.
+fn register_values(value: u32, width: u8) -> Vec<u16> {
+    match width {
+        2 => vec![value as u16, (value >> 16) as u16],
+        _ => vec![value as u16],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::rtu;
+    use futures::executor::block_on;
+
+    /// Replays a capture in small reads, as a serial port would deliver it.
+    struct Replay(std::collections::VecDeque<u8>);
+
+    impl embedded_io_async::ErrorType for Replay {
+        type Error = core::convert::Infallible;
+    }
+
+    impl embedded_io_async::Read for Replay {
+        async fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+            let n = buf.len().min(self.0.len()).min(5);
+            buf.iter_mut()
+                .zip(self.0.drain(..n))
+                .for_each(|(b, r)| *b = r);
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn pairs_requests_with_responses_and_resyncs() {
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um: 1000 };
+        let telemetry = MotorCommandResponsePDUPayload {
+            position_um: 990,
+            force_mn: 12,
+            power_w: 3,
+            temperature_c: 30,
+            voltage_mv: 24000,
+            error: OrcaErrors::default(),
+        };
+        let request = OrcaHighSpeedRequestADU::new(1, OrcaHighSpeedRequestPDU::Command(command));
+        let mut response = vec![1, 0x64];
+        response.extend_from_slice(&990i32.to_be_bytes());
+        response.extend_from_slice(&12i32.to_be_bytes());
+        response.extend_from_slice(&[0, 3, 30, 0x5D, 0xC0, 0, 0]);
+
+        let mut capture = vec![0xFF, 0x00];
+        capture.extend(request.to_vec());
+        capture.extend(rtu(&response));
+        capture.extend(rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]));
+        capture.extend(rtu(&[1, 0x03, 0x02, 0x00, 0x03]));
+        capture.extend(rtu(&[1, 0x06, 0x00, 0x03, 0x00, 0x01]));
+        capture.extend(rtu(&[1, 0x06, 0x00, 0x03, 0x00, 0x01]));
+
+        let mut listener = OrcaListener::new(Replay(capture.into_iter().collect()));
+        let events: Vec<_> = core::iter::from_fn(|| block_on(listener.next_event()).ok()).collect();
+        assert_eq!(
+            events,
+            vec![
+                ListenerEvent::Resynced { skipped: 2 },
+                ListenerEvent::SetpointSent { slave: 1, command },
+                ListenerEvent::TelemetryReturned {
+                    slave: 1,
+                    mode: None,
+                    telemetry
+                },
+                ListenerEvent::RegistersRead {
+                    slave: 1,
+                    address: 317,
+                    values: vec![3]
+                },
+                ListenerEvent::RegisterWritten {
+                    slave: 1,
+                    address: 3,
+                    values: vec![1]
+                },
+            ]
+        );
+        assert_eq!(block_on(listener.next_event()), Err(Error::UnexpectedEof));
+    }
+}
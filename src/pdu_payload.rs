@@ -1,110 +1,482 @@
-extern crate alloc;
+use crate::crc;
 use crate::register_map::OrcaModeOfOperation;
-use alloc::{vec, vec::Vec};
-use binrw::{BinRead, BinWrite, binrw, io::Cursor};
+use crate::{Error, Result};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
+use binrw::{BinRead, BinWrite};
 use bondrewd::Bitfields;
+use core::fmt;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 
-fn check_adu_crc(data: &[u8]) -> bool {
-    let crc = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS);
-    let checksum = crc.checksum(&data[..data.len() - 2]);
-    let received_crc = u16::from_le_bytes([data[data.len() - 2], data[data.len() - 1]]);
-    checksum == received_crc
+pub(crate) fn check_adu_crc(data: &[u8]) -> bool {
+    let mut digest = crc::Digest::new();
+    digest.update(data);
+    data.len() >= 2 && digest.matches()
 }
 
-#[binrw]
-#[brw(big)]
+/// Slave address and CRC around every high-speed PDU.
+pub const HIGH_SPEED_ADU_OVERHEAD: usize = 3;
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+/// Longest high-speed ADU in either direction.
+pub const MAX_HIGH_SPEED_ADU_LEN: usize = HIGH_SPEED_ADU_OVERHEAD
+    + max(
+        OrcaHighSpeedRequestPDU::MAX_LEN,
+        OrcaHighSpeedResponsePDU::MAX_LEN,
+    );
+
+/// A high-speed ADU encoded on the stack.
+pub type HighSpeedFrame = heapless::Vec<u8, MAX_HIGH_SPEED_ADU_LEN>;
+
+/// The Manage request that turns high-speed mode off on `slave_address`. Being `const`, it is
+/// encoded at compile time for a constant address.
+pub const fn disable_high_speed_frame(slave_address: u8) -> [u8; 12] {
+    let manage = FunctionCode::Manage as u8;
+    crc::with_crc([slave_address, manage, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+}
+
+/// The command stream request that puts `slave_address` to sleep, encoded like
+/// [`disable_high_speed_frame`].
+pub const fn sleep_stream_frame(slave_address: u8) -> [u8; 9] {
+    let command = FunctionCode::Command as u8;
+    crc::with_crc([slave_address, command, 0, 0, 0, 0, 0, 0, 0])
+}
+
+/// Appends big-endian fields to a caller-provided buffer.
+struct FrameWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> FrameWriter<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(Error::BufferTooSmall)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+
+    fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Takes big-endian fields off the front of a slice.
+struct FrameReader<'a> {
+    buf: &'a [u8],
+}
+
+impl FrameReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let (head, rest) = self.buf.split_first_chunk().ok_or(Error::Malformed)?;
+        self.buf = rest;
+        Ok(*head)
+    }
+}
+
+/// Encoded size of a fixed-size frame part, in bytes.
+pub trait EncodedLen {
+    const LEN: usize;
+}
+
+/// Wire layout of a frame part. Works on slices, so the driver needs neither binrw nor a heap.
+///
+/// Frame parts are declared through `wire_struct!`, `wire_pdu!` and `mapped_codec!`, which
+/// generate this codec and the binrw one from the same definition.
+trait Codec: Sized {
+    fn encode(&self, w: &mut FrameWriter) -> Result<()>;
+    fn decode(r: &mut FrameReader) -> Result<Self>;
+}
+
+macro_rules! be_codec {
+    ($($ty:ty),*) => {$(
+        impl EncodedLen for $ty {
+            const LEN: usize = size_of::<$ty>();
+        }
+
+        impl Codec for $ty {
+            fn encode(&self, w: &mut FrameWriter) -> Result<()> {
+                w.put(&self.to_be_bytes())
+            }
+            fn decode(r: &mut FrameReader) -> Result<Self> {
+                Ok(<$ty>::from_be_bytes(r.take()?))
+            }
+        }
+    )*};
+}
+be_codec!(u8, u16, u32, i32);
+
+/// Frame parts sent as a `raw` type, converted with `From` on the way out and with `decode` on
+/// the way in.
+macro_rules! mapped_codec {
+    ($($ty:ty as $raw:ty, $decode:expr;)*) => {$(
+        impl EncodedLen for $ty {
+            const LEN: usize = <$raw>::LEN;
+        }
+
+        impl Codec for $ty {
+            fn encode(&self, w: &mut FrameWriter) -> Result<()> {
+                <$raw>::from(*self).encode(w)
+            }
+            fn decode(r: &mut FrameReader) -> Result<Self> {
+                $decode(<$raw>::decode(r)?)
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl binrw::meta::ReadEndian for $ty {
+            const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+        }
+
+        #[cfg(feature = "alloc")]
+        impl binrw::meta::WriteEndian for $ty {
+            const ENDIAN: binrw::meta::EndianKind = binrw::meta::EndianKind::Endian(binrw::Endian::Big);
+        }
+
+        #[cfg(feature = "alloc")]
+        impl BinRead for $ty {
+            type Args<'a> = ();
+
+            fn read_options<R: binrw::io::Read + binrw::io::Seek>(
+                reader: &mut R,
+                endian: binrw::Endian,
+                (): Self::Args<'_>,
+            ) -> binrw::BinResult<Self> {
+                let pos = reader.stream_position()?;
+                let raw = <$raw>::read_options(reader, endian, ())?;
+                $decode(raw).map_err(|err: Error| binrw::Error::Custom {
+                    pos,
+                    err: alloc::boxed::Box::new(err),
+                })
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl BinWrite for $ty {
+            type Args<'a> = ();
+
+            fn write_options<W: binrw::io::Write + binrw::io::Seek>(
+                &self,
+                writer: &mut W,
+                endian: binrw::Endian,
+                (): Self::Args<'_>,
+            ) -> binrw::BinResult<()> {
+                <$raw>::from(*self).write_options(writer, endian, ())
+            }
+        }
+    )*};
+}
+
+/// Declares structs whose fields are sent in order, big-endian, deriving the binrw codec and
+/// the slice codec from the one field list.
+macro_rules! wire_struct {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty,)*
+        }
+    )*) => {$(
+        #[cfg_attr(feature = "alloc", derive(BinRead, BinWrite), brw(big))]
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty,)*
+        }
+
+        impl EncodedLen for $name {
+            const LEN: usize = 0 $(+ <$ty as EncodedLen>::LEN)*;
+        }
+
+        impl Codec for $name {
+            fn encode(&self, w: &mut FrameWriter) -> Result<()> {
+                $(self.$field.encode(w)?;)*
+                Ok(())
+            }
+            fn decode(r: &mut FrameReader) -> Result<Self> {
+                Ok(Self {
+                    $($field: Codec::decode(r)?,)*
+                })
+            }
+        }
+    )*};
+}
+
+/// Declares PDU enums tagged by a function code byte, deriving both codecs like
+/// `wire_struct!`.
+macro_rules! wire_pdu {
+    ($(
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($variant:ident($payload:ty) = $code:tt,)*
+        }
+    )*) => {$(
+        #[cfg_attr(feature = "alloc", derive(BinRead, BinWrite), brw(big))]
+        $(#[$meta])*
+        pub enum $name {
+            $(
+                #[cfg_attr(feature = "alloc", brw(magic = $code))]
+                $variant($payload),
+            )*
+        }
+
+        impl Codec for $name {
+            fn encode(&self, w: &mut FrameWriter) -> Result<()> {
+                match self {
+                    $(Self::$variant(payload) => {
+                        $code.encode(w)?;
+                        payload.encode(w)
+                    })*
+                }
+            }
+            fn decode(r: &mut FrameReader) -> Result<Self> {
+                Ok(match u8::decode(r)? {
+                    $($code => Self::$variant(Codec::decode(r)?),)*
+                    _ => return Err(Error::Malformed),
+                })
+            }
+        }
+    )*};
+}
+
+mapped_codec! {
+    OrcaModeOfOperation as u8, |raw| Ok(OrcaModeOfOperation::try_from(raw).unwrap_or_default());
+    OrcaErrors as u16, |raw| Ok(OrcaErrors::from(raw));
+    ManageHighSpeedRequestSubFunctionCode as u16,
+        |raw| ManageHighSpeedRequestSubFunctionCode::try_from(raw).map_err(|_| Error::Malformed);
+    MotorCommandRequestPDUPayload as RawCommandPayload,
+        |raw| Ok(MotorCommandRequestPDUPayload::from(raw));
+}
+
+/// Encodes an ADU body followed by its CRC.
+fn encode_adu(buf: &mut [u8], slave_address: u8, pdu: &impl Codec) -> Result<usize> {
+    let mut w = FrameWriter::new(buf);
+    slave_address.encode(&mut w)?;
+    pdu.encode(&mut w)?;
+    let crc = crc::checksum(w.written());
+    w.put(&crc.to_le_bytes())?;
+    Ok(w.len)
+}
+
+/// Decodes an ADU spanning all of `bytes`, checking its CRC first.
+fn decode_adu<P: Codec>(bytes: &[u8]) -> Result<(u8, P, u16)> {
+    if bytes.len() < 4 {
+        return Err(Error::Malformed);
+    }
+    if !check_adu_crc(bytes) {
+        return Err(Error::Crc);
+    }
+    let (body, crc) = bytes.split_at(bytes.len() - 2);
+    let mut r = FrameReader { buf: body };
+    let slave_address = u8::decode(&mut r)?;
+    let pdu = P::decode(&mut r)?;
+    if !r.buf.is_empty() {
+        return Err(Error::Malformed);
+    }
+    Ok((slave_address, pdu, u16::from_le_bytes([crc[0], crc[1]])))
+}
+
+#[cfg_attr(feature = "alloc", derive(BinRead, BinWrite), brw(big))]
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
 pub struct OrcaHighSpeedRequestADU {
     pub slave_address: u8,
     pub pdu: OrcaHighSpeedRequestPDU,
-    #[brw(little)]
+    #[cfg_attr(feature = "alloc", brw(little))]
     crc: u16,
 }
 
 impl OrcaHighSpeedRequestADU {
     pub fn new(slave_address: u8, pdu: OrcaHighSpeedRequestPDU) -> Self {
-        let mut adu = vec![slave_address];
-        let mut pdu_bytes = vec![];
-        pdu.write(&mut Cursor::new(&mut pdu_bytes)).unwrap();
-        adu.extend_from_slice(&pdu_bytes);
-        let crc = crc::Crc::<u16>::new(&crc::CRC_16_MODBUS);
-        let checksum = crc.checksum(&adu);
+        let mut buf = [0u8; MAX_HIGH_SPEED_ADU_LEN];
+        let len = encode_adu(&mut buf, slave_address, &pdu).expect("request ADU fits");
+        let crc = u16::from_le_bytes([buf[len - 2], buf[len - 1]]);
         Self {
             slave_address,
             pdu,
-            crc: checksum,
+            crc,
         }
     }
 
+    /// Decodes a request ADU spanning all of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (slave_address, pdu, crc) = decode_adu(bytes)?;
+        Ok(Self {
+            slave_address,
+            pdu,
+            crc,
+        })
+    }
+
+    /// Encodes the ADU into `buf` and returns its length.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize> {
+        encode_adu(buf, self.slave_address, &self.pdu)
+    }
+
+    pub fn to_frame(&self) -> HighSpeedFrame {
+        self.pdu.to_frame(self.slave_address)
+    }
+
+    #[cfg(feature = "alloc")]
     pub fn to_vec(&self) -> Vec<u8> {
-        let mut cur = Cursor::new(Vec::new());
-        self.write(&mut cur).expect("binrw write failed");
-        cur.into_inner()
+        self.to_frame().to_vec()
     }
 
-    pub fn num_response_bytes(&self) -> usize {
-        1 + match self.pdu {
-            OrcaHighSpeedRequestPDU::Manage(_) => 9,
-            OrcaHighSpeedRequestPDU::Command(_) => 16,
-            OrcaHighSpeedRequestPDU::Read(_) => 21,
-            OrcaHighSpeedRequestPDU::Write(_) => 17,
-        } + 2
+    pub const fn num_response_bytes(&self) -> usize {
+        HIGH_SPEED_ADU_OVERHEAD + self.pdu.response_len()
     }
 }
 
-#[binrw]
-#[brw(big)]
+#[cfg_attr(feature = "alloc", derive(BinRead, BinWrite), brw(big))]
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
 pub struct OrcaHighSpeedResponseADU {
     pub slave_address: u8,
     pub pdu: OrcaHighSpeedResponsePDU,
-    #[brw(little)]
+    #[cfg_attr(feature = "alloc", brw(little))]
     crc: u16,
 }
 impl OrcaHighSpeedResponseADU {
-    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
-        let mut cursor = Cursor::new(bytes);
-        let adu = Self::read(&mut cursor)?;
-        if !check_adu_crc(bytes) {
-            anyhow::bail!("CRC check failed");
+    pub fn new(slave_address: u8, pdu: OrcaHighSpeedResponsePDU) -> Self {
+        let mut buf = [0u8; MAX_HIGH_SPEED_ADU_LEN];
+        let len = encode_adu(&mut buf, slave_address, &pdu).expect("response ADU fits");
+        let crc = u16::from_le_bytes([buf[len - 2], buf[len - 1]]);
+        Self {
+            slave_address,
+            pdu,
+            crc,
         }
-        Ok(adu)
+    }
+
+    /// Decodes a response ADU spanning all of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (slave_address, pdu, crc) = decode_adu(bytes)?;
+        Ok(Self {
+            slave_address,
+            pdu,
+            crc,
+        })
+    }
+
+    /// Encodes the ADU into `buf` and returns its length.
+    pub fn encode_into(&self, buf: &mut [u8]) -> Result<usize> {
+        encode_adu(buf, self.slave_address, &self.pdu)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = [0u8; MAX_HIGH_SPEED_ADU_LEN];
+        let len = self.encode_into(&mut buf).expect("response ADU fits");
+        buf[..len].to_vec()
     }
 }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub enum OrcaHighSpeedRequestPDU {
-    #[brw(magic = 0x41u8)]
-    Manage(ManageHighSpeedRequestPDUPayload),
-    #[brw(magic = 0x64u8)]
-    Command(MotorCommandRequestPDUPayload),
-    #[brw(magic = 0x68u8)]
-    Read(MotorReadRequestPDUPayload),
-    #[brw(magic = 0x69u8)]
-    Write(MotorWriteRequestPDUPayload),
-}
-
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub enum OrcaHighSpeedResponsePDU {
-    #[brw(magic = 0x41u8)]
-    Manage(ManageHighSpeedResponsePDUPayload),
-    #[brw(magic = 0x64u8)]
-    Command(MotorCommandResponsePDUPayload),
-    #[brw(magic = 0x68u8)]
-    Read(MotorReadResponsePDUPayload),
-    #[brw(magic = 0x69u8)]
-    Write(MotorWriteResponsePDUPayload),
+wire_pdu! {
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub enum OrcaHighSpeedRequestPDU {
+        Manage(ManageHighSpeedRequestPDUPayload) = 0x41u8,
+        Command(MotorCommandRequestPDUPayload) = 0x64u8,
+        Read(MotorReadRequestPDUPayload) = 0x68u8,
+        Write(MotorWriteRequestPDUPayload) = 0x69u8,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub enum OrcaHighSpeedResponsePDU {
+        Manage(ManageHighSpeedResponsePDUPayload) = 0x41u8,
+        Command(MotorCommandResponsePDUPayload) = 0x64u8,
+        Read(MotorReadResponsePDUPayload) = 0x68u8,
+        Write(MotorWriteResponsePDUPayload) = 0x69u8,
+    }
+}
+
+impl OrcaHighSpeedRequestPDU {
+    /// Longest request PDU, function code included.
+    pub const MAX_LEN: usize = 1 + max(
+        max(
+            ManageHighSpeedRequestPDUPayload::LEN,
+            MotorCommandRequestPDUPayload::LEN,
+        ),
+        max(
+            MotorReadRequestPDUPayload::LEN,
+            MotorWriteRequestPDUPayload::LEN,
+        ),
+    );
+
+    /// Encoded length, function code included.
+    pub const fn encoded_len(&self) -> usize {
+        1 + match self {
+            Self::Manage(_) => ManageHighSpeedRequestPDUPayload::LEN,
+            Self::Command(_) => MotorCommandRequestPDUPayload::LEN,
+            Self::Read(_) => MotorReadRequestPDUPayload::LEN,
+            Self::Write(_) => MotorWriteRequestPDUPayload::LEN,
+        }
+    }
+
+    /// Length of the response PDU the motor answers this request with.
+    pub const fn response_len(&self) -> usize {
+        1 + match self {
+            Self::Manage(_) => ManageHighSpeedResponsePDUPayload::LEN,
+            Self::Command(_) => MotorCommandResponsePDUPayload::LEN,
+            Self::Read(_) => MotorReadResponsePDUPayload::LEN,
+            Self::Write(_) => MotorWriteResponsePDUPayload::LEN,
+        }
+    }
+
+    /// Encodes the ADU for `slave_address` on the stack, computing the CRC once.
+    pub fn to_frame(&self, slave_address: u8) -> HighSpeedFrame {
+        let mut frame = HighSpeedFrame::new();
+        frame
+            .resize_default(HIGH_SPEED_ADU_OVERHEAD + self.encoded_len())
+            .expect("frame capacity");
+        encode_adu(&mut frame, slave_address, self).expect("request ADU fits");
+        frame
+    }
+}
+
+impl OrcaHighSpeedResponsePDU {
+    /// Longest response PDU, function code included.
+    pub const MAX_LEN: usize = 1 + max(
+        max(
+            ManageHighSpeedResponsePDUPayload::LEN,
+            MotorCommandResponsePDUPayload::LEN,
+        ),
+        max(
+            MotorReadResponsePDUPayload::LEN,
+            MotorWriteResponsePDUPayload::LEN,
+        ),
+    );
+
+    /// Encoded length, function code included.
+    pub const fn encoded_len(&self) -> usize {
+        1 + match self {
+            Self::Manage(_) => ManageHighSpeedResponsePDUPayload::LEN,
+            Self::Command(_) => MotorCommandResponsePDUPayload::LEN,
+            Self::Read(_) => MotorReadResponsePDUPayload::LEN,
+            Self::Write(_) => MotorWriteResponsePDUPayload::LEN,
+        }
+    }
+
+    /// Telemetry carried by command, read and write responses.
+    pub fn command_response(&self) -> Option<&MotorCommandResponsePDUPayload> {
+        match self {
+            Self::Manage(_) => None,
+            Self::Command(payload) => Some(payload),
+            Self::Read(payload) => Some(&payload.command_response),
+            Self::Write(payload) => Some(&payload.command_response),
+        }
+    }
 }
 
 #[repr(u8)]
-#[binrw]
-#[brw(repr=u8)]
+#[cfg_attr(feature = "alloc", derive(BinRead, BinWrite), brw(repr=u8))]
 #[derive(
     Debug, PartialEq, Eq, IntoPrimitive, TryFromPrimitive, Copy, Clone, Deserialize, Serialize,
 )]
@@ -116,8 +488,6 @@ pub enum FunctionCode {
 }
 
 #[repr(u16)]
-#[binrw]
-#[brw(repr=u16)]
 #[derive(
     Debug,
     PartialEq,
@@ -136,50 +506,84 @@ pub enum ManageHighSpeedRequestSubFunctionCode {
     Disable = 0x0000,
 }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Default, Copy, Clone, Deserialize, Serialize)]
-pub struct ManageHighSpeedRequestPDUPayload {
-    pub sub_function_code: ManageHighSpeedRequestSubFunctionCode,
-    pub baud_rate: u32,
-    pub delay_us: u16,
-}
+wire_struct! {
+    #[derive(Debug, PartialEq, Eq, Default, Copy, Clone, Deserialize, Serialize)]
+    pub struct ManageHighSpeedRequestPDUPayload {
+        pub sub_function_code: ManageHighSpeedRequestSubFunctionCode,
+        pub baud_rate: u32,
+        pub delay_us: u16,
+    }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct ManageHighSpeedResponsePDUPayload {
-    pub state_command: ManageHighSpeedRequestSubFunctionCode,
-    pub baud_rate: u32,
-    pub delay_us: u16,
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct ManageHighSpeedResponsePDUPayload {
+        pub state_command: ManageHighSpeedRequestSubFunctionCode,
+        pub baud_rate: u32,
+        pub delay_us: u16,
+    }
+
+    /// Every command payload is a sub-function code followed by four data bytes.
+    #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+    struct RawCommandPayload {
+        sub_function_code: u8,
+        data: u32,
+    }
 }
 
-#[binrw]
-#[brw(big)]
+/// Sent as a [`RawCommandPayload`].
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
 pub enum MotorCommandRequestPDUPayload {
-    #[brw(magic = 0x1Cu8)]
-    ForceControlStream { force_mn: i32 },
-
-    #[brw(magic = 0x1Eu8)]
-    PositionControlStream { position_um: i32 },
-
-    #[brw(magic = 0x20u8)]
-    KinematicDataStream {
-        #[br(temp)]
-        #[brw(calc = 0u32)]
-        _pad4: u32,
+    ForceControlStream {
+        force_mn: i32,
+    },
+    PositionControlStream {
+        position_um: i32,
     },
+    KinematicDataStream {},
+    HapticDataStream {
+        haptic_status_register: u32,
+    },
+    /// Any other sub-function code puts the motor to sleep.
+    SleepDataStream {},
+}
 
-    #[brw(magic = 0x22u8)]
-    HapticDataStream { haptic_status_register: u32 },
+impl From<RawCommandPayload> for MotorCommandRequestPDUPayload {
+    fn from(raw: RawCommandPayload) -> Self {
+        match raw.sub_function_code {
+            0x1C => Self::ForceControlStream {
+                force_mn: raw.data as i32,
+            },
+            0x1E => Self::PositionControlStream {
+                position_um: raw.data as i32,
+            },
+            0x20 => Self::KinematicDataStream {},
+            0x22 => Self::HapticDataStream {
+                haptic_status_register: raw.data,
+            },
+            _ => Self::SleepDataStream {},
+        }
+    }
+}
 
-    SleepDataStream {
-        #[brw(pad_after = 4)]
-        #[br(temp)]
-        #[brw(calc = 0u8)]
-        _unknown_subcode: u8,
-    },
+impl From<MotorCommandRequestPDUPayload> for RawCommandPayload {
+    fn from(payload: MotorCommandRequestPDUPayload) -> Self {
+        let (sub_function_code, data) = match payload {
+            MotorCommandRequestPDUPayload::ForceControlStream { force_mn } => {
+                (0x1C, force_mn as u32)
+            }
+            MotorCommandRequestPDUPayload::PositionControlStream { position_um } => {
+                (0x1E, position_um as u32)
+            }
+            MotorCommandRequestPDUPayload::KinematicDataStream {} => (0x20, 0),
+            MotorCommandRequestPDUPayload::HapticDataStream {
+                haptic_status_register,
+            } => (0x22, haptic_status_register),
+            MotorCommandRequestPDUPayload::SleepDataStream {} => (0x00, 0),
+        };
+        Self {
+            sub_function_code,
+            data,
+        }
+    }
 }
 
 #[derive(Bitfields, Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize, Serialize)]
@@ -202,6 +606,77 @@ pub struct OrcaErrors {
     pub reserve2: u16,
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub enum OrcaErrorFlag {
+    ConfigurationErrors,
+    ForceClipping,
+    TemperatureExceeded,
+    ForceExceeded,
+    PowerExceeded,
+    ShaftImageFailed,
+    VoltageInvalid,
+    CommunicationTimeout,
+    AutoZeroFailed,
+}
+
+impl OrcaErrorFlag {
+    pub const ALL: [Self; 9] = [
+        Self::ConfigurationErrors,
+        Self::ForceClipping,
+        Self::TemperatureExceeded,
+        Self::ForceExceeded,
+        Self::PowerExceeded,
+        Self::ShaftImageFailed,
+        Self::VoltageInvalid,
+        Self::CommunicationTimeout,
+        Self::AutoZeroFailed,
+    ];
+
+    /// Name of the matching `OrcaErrors` field.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ConfigurationErrors => "configuration_errors",
+            Self::ForceClipping => "force_clipping",
+            Self::TemperatureExceeded => "temperature_exceeded",
+            Self::ForceExceeded => "force_exceeded",
+            Self::PowerExceeded => "power_exceeded",
+            Self::ShaftImageFailed => "shaft_image_failed",
+            Self::VoltageInvalid => "voltage_invalid",
+            Self::CommunicationTimeout => "communication_timeout",
+            Self::AutoZeroFailed => "auto_zero_failed",
+        }
+    }
+}
+
+impl fmt::Display for OrcaErrorFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl OrcaErrors {
+    pub fn contains(&self, flag: OrcaErrorFlag) -> bool {
+        match flag {
+            OrcaErrorFlag::ConfigurationErrors => self.configuration_errors,
+            OrcaErrorFlag::ForceClipping => self.force_clipping,
+            OrcaErrorFlag::TemperatureExceeded => self.temperature_exceeded,
+            OrcaErrorFlag::ForceExceeded => self.force_exceeded,
+            OrcaErrorFlag::PowerExceeded => self.power_exceeded,
+            OrcaErrorFlag::ShaftImageFailed => self.shaft_image_failed,
+            OrcaErrorFlag::VoltageInvalid => self.voltage_invalid,
+            OrcaErrorFlag::CommunicationTimeout => self.communication_timeout,
+            OrcaErrorFlag::AutoZeroFailed => self.auto_zero_failed,
+        }
+    }
+
+    /// Flags that are set, in bit order.
+    pub fn active(&self) -> impl Iterator<Item = OrcaErrorFlag> + '_ {
+        OrcaErrorFlag::ALL
+            .into_iter()
+            .filter(|flag| self.contains(*flag))
+    }
+}
+
 impl From<u16> for OrcaErrors {
     fn from(value: u16) -> Self {
         Self::from_bytes(value.to_be_bytes())
@@ -213,63 +688,191 @@ impl From<OrcaErrors> for u16 {
     }
 }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct MotorCommandResponsePDUPayload {
-    pub position_um: i32,
-    pub force_mn: i32,
-    pub power_w: u16,
-    pub temperature_c: u8,
-    pub voltage_mv: u16,
-    #[br(map=|x: u16| OrcaErrors::from(x))]
-    #[bw(map=|x: &OrcaErrors| <u16 as From<OrcaErrors>>::from(*x))]
-    pub error: OrcaErrors,
-}
-
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct MotorReadRequestPDUPayload {
-    pub register_address: u16,
-    pub register_width: u8,
-}
+wire_struct! {
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct MotorCommandResponsePDUPayload {
+        pub position_um: i32,
+        pub force_mn: i32,
+        pub power_w: u16,
+        pub temperature_c: u8,
+        pub voltage_mv: u16,
+        pub error: OrcaErrors,
+    }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct MotorReadResponsePDUPayload {
-    pub read_register_value: u32,
-    #[br(map=|x: u8| OrcaModeOfOperation::try_from(x).unwrap_or_default())]
-    #[bw(map=|x: &OrcaModeOfOperation| <u8 as From<OrcaModeOfOperation>>::from(*x))]
-    pub mode_of_operation: OrcaModeOfOperation,
-    pub command_response: MotorCommandResponsePDUPayload,
-}
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct MotorReadRequestPDUPayload {
+        pub register_address: u16,
+        pub register_width: u8,
+    }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct MotorWriteRequestPDUPayload {
-    pub register_address: u16,
-    pub register_width: u8,
-    pub register_data: u32,
-}
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct MotorReadResponsePDUPayload {
+        pub read_register_value: u32,
+        pub mode_of_operation: OrcaModeOfOperation,
+        pub command_response: MotorCommandResponsePDUPayload,
+    }
 
-#[binrw]
-#[brw(big)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
-pub struct MotorWriteResponsePDUPayload {
-    #[br(map=|x: u8| OrcaModeOfOperation::try_from(x).unwrap_or_default())]
-    #[bw(map=|x: &OrcaModeOfOperation| <u8 as From<OrcaModeOfOperation>>::from(*x))]
-    pub mode_of_operation: OrcaModeOfOperation,
-    pub command_response: MotorCommandResponsePDUPayload,
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct MotorWriteRequestPDUPayload {
+        pub register_address: u16,
+        pub register_width: u8,
+        pub register_data: u32,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+    pub struct MotorWriteResponsePDUPayload {
+        pub mode_of_operation: OrcaModeOfOperation,
+        pub command_response: MotorCommandResponsePDUPayload,
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
+    use alloc::vec;
     use binrw::BinRead;
     use binrw::io::Cursor;
+    use proptest::prelude::*;
+
+    #[test]
+    fn slice_codec_matches_binrw() {
+        let pdus = [
+            OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload {
+                sub_function_code: ManageHighSpeedRequestSubFunctionCode::Enable,
+                baud_rate: 625000,
+                delay_us: 50,
+            }),
+            OrcaHighSpeedRequestPDU::Command(
+                MotorCommandRequestPDUPayload::PositionControlStream {
+                    position_um: -12000,
+                },
+            ),
+            OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::KinematicDataStream {}),
+            OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::SleepDataStream {}),
+            OrcaHighSpeedRequestPDU::Read(MotorReadRequestPDUPayload {
+                register_address: 342,
+                register_width: 2,
+            }),
+            OrcaHighSpeedRequestPDU::Write(MotorWriteRequestPDUPayload {
+                register_address: 3,
+                register_width: 1,
+                register_data: 1,
+            }),
+        ];
+        for pdu in pdus {
+            let adu = OrcaHighSpeedRequestADU::new(3, pdu);
+            let mut cursor = Cursor::new(Vec::new());
+            adu.write(&mut cursor).unwrap();
+            assert_eq!(adu.to_frame()[..], cursor.get_ref()[..]);
+            assert_eq!(
+                OrcaHighSpeedRequestADU::from_bytes(cursor.get_ref()),
+                Ok(adu)
+            );
+        }
+    }
+
+    fn written_len<T>(value: &T) -> usize
+    where
+        T: BinWrite + binrw::meta::WriteEndian,
+        for<'a> T::Args<'a>: Default,
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        value.write(&mut cursor).unwrap();
+        cursor.into_inner().len()
+    }
+
+    #[test]
+    fn encoded_lengths_match_binrw() {
+        let command_response = MotorCommandResponsePDUPayload {
+            position_um: 0,
+            force_mn: 0,
+            power_w: 0,
+            temperature_c: 0,
+            voltage_mv: 0,
+            error: OrcaErrors::default(),
+        };
+        let manage = OrcaHighSpeedResponsePDU::Manage(ManageHighSpeedResponsePDUPayload {
+            state_command: ManageHighSpeedRequestSubFunctionCode::Disable,
+            baud_rate: 0,
+            delay_us: 0,
+        });
+        let read = OrcaHighSpeedResponsePDU::Read(MotorReadResponsePDUPayload {
+            read_register_value: 0,
+            mode_of_operation: OrcaModeOfOperation::SleepMode,
+            command_response,
+        });
+        let write = OrcaHighSpeedResponsePDU::Write(MotorWriteResponsePDUPayload {
+            mode_of_operation: OrcaModeOfOperation::SleepMode,
+            command_response,
+        });
+        let exchanges = [
+            (
+                OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload::default()),
+                manage,
+            ),
+            (
+                OrcaHighSpeedRequestPDU::Command(
+                    MotorCommandRequestPDUPayload::ForceControlStream { force_mn: 0 },
+                ),
+                OrcaHighSpeedResponsePDU::Command(command_response),
+            ),
+            (
+                OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::SleepDataStream {}),
+                OrcaHighSpeedResponsePDU::Command(command_response),
+            ),
+            (
+                OrcaHighSpeedRequestPDU::Read(MotorReadRequestPDUPayload {
+                    register_address: 0,
+                    register_width: 1,
+                }),
+                read,
+            ),
+            (
+                OrcaHighSpeedRequestPDU::Write(MotorWriteRequestPDUPayload {
+                    register_address: 0,
+                    register_width: 1,
+                    register_data: 0,
+                }),
+                write,
+            ),
+        ];
+        for (request, response) in exchanges {
+            assert_eq!(request.encoded_len(), written_len(&request));
+            assert_eq!(request.response_len(), written_len(&response));
+            assert_eq!(response.encoded_len(), written_len(&response));
+            assert!(request.encoded_len() <= OrcaHighSpeedRequestPDU::MAX_LEN);
+            assert!(response.encoded_len() <= OrcaHighSpeedResponsePDU::MAX_LEN);
+
+            let adu = OrcaHighSpeedRequestADU::new(1, request);
+            assert_eq!(adu.to_frame().len(), written_len(&adu));
+        }
+        assert_eq!(MAX_HIGH_SPEED_ADU_LEN, 24);
+    }
+
+    #[test]
+    fn response_adu_rejects_bad_crc() {
+        let mut bytes = vec![
+            0x01, 0x64, 0x00, 0x00, 0x2E, 0xE0, 0x00, 0x01, 0x38, 0x80, 0x00, 0x19, 0x18, 0x5E,
+            0x56, 0x00, 0x00,
+        ];
+        let crc = crc::checksum(&bytes);
+        bytes.extend_from_slice(&crc.to_le_bytes());
+        let adu = OrcaHighSpeedResponseADU::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            adu.pdu.command_response().map(|r| r.position_um),
+            Some(12000)
+        );
+        assert_eq!(
+            OrcaHighSpeedResponseADU::read(&mut Cursor::new(&bytes)).unwrap(),
+            adu
+        );
+
+        bytes[3] ^= 0x01;
+        assert_eq!(
+            OrcaHighSpeedResponseADU::from_bytes(&bytes),
+            Err(Error::Crc)
+        );
+    }
 
     #[test]
     fn manage_high_speed_stream_frame() {
@@ -309,6 +912,19 @@ mod tests {
         assert_eq!(deserialized_request, expected_request);
     }
 
+    #[test]
+    fn fixed_frames_match_codec() {
+        const SLEEP: [u8; 9] = sleep_stream_frame(1);
+        let sleep =
+            OrcaHighSpeedRequestPDU::Command(MotorCommandRequestPDUPayload::SleepDataStream {});
+        assert_eq!(SLEEP[..], sleep.to_frame(1));
+        let disable = OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload::default());
+        for slave in [0, 1, 0x7F, 0xFF] {
+            assert_eq!(sleep_stream_frame(slave)[..], sleep.to_frame(slave));
+            assert_eq!(disable_high_speed_frame(slave)[..], disable.to_frame(slave));
+        }
+    }
+
     #[test]
     fn force_control_stream_command_frame() {
         let mut bytes_request = Cursor::new(vec![0x1C, 0x00, 0x00, 0x03, 0xE8]);
@@ -337,4 +953,264 @@ mod tests {
         };
         assert_eq!(deserialized_response, expected_response);
     }
+
+    #[test]
+    fn active_error_flags() {
+        let errors = OrcaErrors::from(0x2021);
+        assert_eq!(
+            errors.active().collect::<Vec<_>>(),
+            [
+                OrcaErrorFlag::ConfigurationErrors,
+                OrcaErrorFlag::ForceClipping,
+                OrcaErrorFlag::AutoZeroFailed
+            ]
+        );
+        assert_eq!(OrcaErrors::default().active().count(), 0);
+    }
+
+    fn mode() -> impl Strategy<Value = OrcaModeOfOperation> {
+        prop::sample::select(
+            &[
+                OrcaModeOfOperation::SleepMode,
+                OrcaModeOfOperation::ForceMode,
+                OrcaModeOfOperation::PositionMode,
+                OrcaModeOfOperation::HapticMode,
+                OrcaModeOfOperation::KineticMode,
+                OrcaModeOfOperation::PulseWidthMode,
+                OrcaModeOfOperation::AutoZeroingMode,
+            ][..],
+        )
+    }
+
+    fn errors() -> impl Strategy<Value = OrcaErrors> {
+        any::<u16>().prop_map(OrcaErrors::from)
+    }
+
+    fn state_command() -> impl Strategy<Value = ManageHighSpeedRequestSubFunctionCode> {
+        prop::sample::select(
+            &[
+                ManageHighSpeedRequestSubFunctionCode::Enable,
+                ManageHighSpeedRequestSubFunctionCode::Disable,
+            ][..],
+        )
+    }
+
+    fn command() -> impl Strategy<Value = MotorCommandRequestPDUPayload> {
+        prop_oneof![
+            any::<i32>().prop_map(
+                |force_mn| MotorCommandRequestPDUPayload::ForceControlStream { force_mn }
+            ),
+            any::<i32>().prop_map(|position_um| {
+                MotorCommandRequestPDUPayload::PositionControlStream { position_um }
+            }),
+            Just(MotorCommandRequestPDUPayload::KinematicDataStream {}),
+            any::<u32>().prop_map(|haptic_status_register| {
+                MotorCommandRequestPDUPayload::HapticDataStream {
+                    haptic_status_register,
+                }
+            }),
+            Just(MotorCommandRequestPDUPayload::SleepDataStream {}),
+        ]
+    }
+
+    fn telemetry() -> impl Strategy<Value = MotorCommandResponsePDUPayload> {
+        (any::<(i32, i32, u16, u8, u16)>(), errors()).prop_map(
+            |((position_um, force_mn, power_w, temperature_c, voltage_mv), error)| {
+                MotorCommandResponsePDUPayload {
+                    position_um,
+                    force_mn,
+                    power_w,
+                    temperature_c,
+                    voltage_mv,
+                    error,
+                }
+            },
+        )
+    }
+
+    fn request() -> impl Strategy<Value = OrcaHighSpeedRequestPDU> {
+        prop_oneof![
+            (state_command(), any::<u32>(), any::<u16>()).prop_map(
+                |(sub_function_code, baud_rate, delay_us)| {
+                    OrcaHighSpeedRequestPDU::Manage(ManageHighSpeedRequestPDUPayload {
+                        sub_function_code,
+                        baud_rate,
+                        delay_us,
+                    })
+                }
+            ),
+            command().prop_map(OrcaHighSpeedRequestPDU::Command),
+            any::<(u16, u8)>().prop_map(|(register_address, register_width)| {
+                OrcaHighSpeedRequestPDU::Read(MotorReadRequestPDUPayload {
+                    register_address,
+                    register_width,
+                })
+            }),
+            any::<(u16, u8, u32)>().prop_map(
+                |(register_address, register_width, register_data)| {
+                    OrcaHighSpeedRequestPDU::Write(MotorWriteRequestPDUPayload {
+                        register_address,
+                        register_width,
+                        register_data,
+                    })
+                }
+            ),
+        ]
+    }
+
+    fn response() -> impl Strategy<Value = OrcaHighSpeedResponsePDU> {
+        prop_oneof![
+            (state_command(), any::<u32>(), any::<u16>()).prop_map(
+                |(state_command, baud_rate, delay_us)| {
+                    OrcaHighSpeedResponsePDU::Manage(ManageHighSpeedResponsePDUPayload {
+                        state_command,
+                        baud_rate,
+                        delay_us,
+                    })
+                }
+            ),
+            telemetry().prop_map(OrcaHighSpeedResponsePDU::Command),
+            (any::<u32>(), mode(), telemetry()).prop_map(
+                |(read_register_value, mode_of_operation, command_response)| {
+                    OrcaHighSpeedResponsePDU::Read(MotorReadResponsePDUPayload {
+                        read_register_value,
+                        mode_of_operation,
+                        command_response,
+                    })
+                }
+            ),
+            (mode(), telemetry()).prop_map(|(mode_of_operation, command_response)| {
+                OrcaHighSpeedResponsePDU::Write(MotorWriteResponsePDUPayload {
+                    mode_of_operation,
+                    command_response,
+                })
+            }),
+        ]
+    }
+
+    /// Writes `value` with binrw and reads it back.
+    fn binrw_round_trip<T>(value: &T) -> T
+    where
+        T: BinRead + BinWrite + binrw::meta::ReadEndian + binrw::meta::WriteEndian,
+        for<'a> <T as BinRead>::Args<'a>: Default,
+        for<'a> <T as BinWrite>::Args<'a>: Default,
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        value.write(&mut cursor).unwrap();
+        cursor.set_position(0);
+        T::read(&mut cursor).unwrap()
+    }
+
+    /// Bytes binrw writes for `value`.
+    fn binrw_bytes<T>(value: &T) -> Vec<u8>
+    where
+        T: BinWrite + binrw::meta::WriteEndian,
+        for<'a> T::Args<'a>: Default,
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        value.write(&mut cursor).unwrap();
+        cursor.into_inner()
+    }
+
+    /// Bytes the slice codec writes for `value`.
+    fn codec_bytes(value: &impl Codec) -> Vec<u8> {
+        let mut buf = [0; MAX_HIGH_SPEED_ADU_LEN];
+        let mut w = FrameWriter::new(&mut buf);
+        value.encode(&mut w).unwrap();
+        w.written().to_vec()
+    }
+
+    /// Both codecs write the same bytes for `value`, and each reads the other's bytes back.
+    fn assert_codecs_agree<T>(value: &T) -> core::result::Result<(), TestCaseError>
+    where
+        T: Codec + BinRead + BinWrite + binrw::meta::ReadEndian + binrw::meta::WriteEndian,
+        T: PartialEq + fmt::Debug,
+        for<'a> <T as BinRead>::Args<'a>: Default,
+        for<'a> <T as BinWrite>::Args<'a>: Default,
+    {
+        let bytes = codec_bytes(value);
+        prop_assert_eq!(&bytes, &binrw_bytes(value));
+        prop_assert_eq!(&T::read(&mut Cursor::new(&bytes)).unwrap(), value);
+        prop_assert_eq!(&T::decode(&mut FrameReader { buf: &bytes }).unwrap(), value);
+        Ok(())
+    }
+
+    /// `frame` with bit `bit` inverted, counting from the first byte's least significant bit.
+    fn flip(frame: &[u8], bit: usize) -> Vec<u8> {
+        let mut frame = frame.to_vec();
+        frame[bit / 8] ^= 1 << (bit % 8);
+        frame
+    }
+
+    proptest! {
+        #[test]
+        fn request_round_trips(slave: u8, pdu in request()) {
+            let adu = OrcaHighSpeedRequestADU::new(slave, pdu);
+            prop_assert_eq!(OrcaHighSpeedRequestADU::from_bytes(&adu.to_frame()), Ok(adu));
+            prop_assert_eq!(binrw_round_trip(&adu), adu);
+            prop_assert_eq!(binrw_round_trip(&pdu), pdu);
+        }
+
+        #[test]
+        fn response_round_trips(slave: u8, pdu in response()) {
+            let adu = OrcaHighSpeedResponseADU::new(slave, pdu);
+            prop_assert_eq!(OrcaHighSpeedResponseADU::from_bytes(&adu.to_vec()), Ok(adu));
+            prop_assert_eq!(binrw_round_trip(&adu), adu);
+            prop_assert_eq!(binrw_round_trip(&pdu), pdu);
+        }
+
+        #[test]
+        fn request_codecs_agree(slave: u8, pdu in request()) {
+            assert_codecs_agree(&pdu)?;
+            let adu = OrcaHighSpeedRequestADU::new(slave, pdu);
+            prop_assert_eq!(adu.to_vec(), binrw_bytes(&adu));
+            prop_assert_eq!(OrcaHighSpeedRequestADU::read(&mut Cursor::new(adu.to_vec())).unwrap(), adu);
+            match pdu {
+                OrcaHighSpeedRequestPDU::Manage(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedRequestPDU::Command(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedRequestPDU::Read(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedRequestPDU::Write(payload) => assert_codecs_agree(&payload)?,
+            }
+        }
+
+        #[test]
+        fn response_codecs_agree(slave: u8, pdu in response()) {
+            assert_codecs_agree(&pdu)?;
+            let adu = OrcaHighSpeedResponseADU::new(slave, pdu);
+            prop_assert_eq!(adu.to_vec(), binrw_bytes(&adu));
+            prop_assert_eq!(OrcaHighSpeedResponseADU::read(&mut Cursor::new(adu.to_vec())).unwrap(), adu);
+            match pdu {
+                OrcaHighSpeedResponsePDU::Manage(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedResponsePDU::Command(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedResponsePDU::Read(payload) => assert_codecs_agree(&payload)?,
+                OrcaHighSpeedResponsePDU::Write(payload) => assert_codecs_agree(&payload)?,
+            }
+        }
+
+        #[test]
+        fn command_payload_round_trips(payload in command()) {
+            prop_assert_eq!(binrw_round_trip(&payload), payload);
+            let raw = RawCommandPayload::from(payload);
+            prop_assert_eq!(MotorCommandRequestPDUPayload::from(raw), payload);
+        }
+
+        #[test]
+        fn errors_round_trip(errors in errors()) {
+            prop_assert_eq!(OrcaErrors::from(u16::from(errors)), errors);
+        }
+
+        #[test]
+        fn request_rejects_any_bit_flip(slave: u8, pdu in request(), bit: prop::sample::Index) {
+            let frame = OrcaHighSpeedRequestADU::new(slave, pdu).to_frame();
+            let flipped = flip(&frame, bit.index(frame.len() * 8));
+            prop_assert_eq!(OrcaHighSpeedRequestADU::from_bytes(&flipped), Err(Error::Crc));
+        }
+
+        #[test]
+        fn response_rejects_any_bit_flip(slave: u8, pdu in response(), bit: prop::sample::Index) {
+            let frame = OrcaHighSpeedResponseADU::new(slave, pdu).to_vec();
+            let flipped = flip(&frame, bit.index(frame.len() * 8));
+            prop_assert_eq!(OrcaHighSpeedResponseADU::from_bytes(&flipped), Err(Error::Crc));
+        }
+    }
 }
@@ -0,0 +1,231 @@
+use bondrewd::Bitfields;
+use core::ops::RangeInclusive;
+use embedded_hal_async::delay::DelayNs;
+use embedded_registers::Register;
+use serde::{Deserialize, Serialize};
+
+use crate::register_map::*;
+use crate::{Error, OrcaMotor, Result};
+
+/// Interval between `CtrlReg2`/`CtrlReg4` polls while the motor writes its flash.
+pub const FLASH_POLL_INTERVAL_MS: u32 = 50;
+
+/// Register blocks known to belong to a parameter group, re-read around a flash write.
+const TUNING: RangeInclusive<u16> = CCPGain::ADDRESS as u16..=PCFSatuH::ADDRESS as u16;
+const MOTOR_USER_OPTIONS: RangeInclusive<u16> =
+    UserMaxTemp::ADDRESS as u16..=PosSign::ADDRESS as u16;
+const MODBUS_USER_OPTIONS: RangeInclusive<u16> =
+    LogPeriod::ADDRESS as u16..=AutoZeroSpeedMmps::ADDRESS as u16;
+const PWM: RangeInclusive<u16> = PwmTimeoutMs::ADDRESS as u16..=PwmServoType::ADDRESS as u16;
+
+/// Most registers all of a flag set's blocks add up to.
+const MAX_GROUP_REGISTERS: usize = 64;
+
+/// Parameter groups that can be saved to flash, laid out like `CtrlReg2`.
+///
+/// Start from `SaveGroups::default()` and set the groups wanted.
+#[derive(Bitfields, Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize, Serialize)]
+#[bondrewd(default_endianness = "le", read_from = "lsb0", enforce_bytes = 2)]
+pub struct SaveGroups {
+    #[bondrewd(bit_length = 4, reserve)]
+    reserve0: u8,
+    pub tuning: bool,
+    pub user_options: bool,
+    pub motion_config: bool,
+    pub iosh: bool,
+    pub haptic_config: bool,
+    #[bondrewd(bit_length = 7, reserve)]
+    reserve1: u8,
+}
+
+impl SaveGroups {
+    /// Register blocks the selected groups cover. The motion, IOSH and haptic registers are
+    /// not mapped, so those groups have none.
+    fn registers(&self) -> impl Iterator<Item = RangeInclusive<u16>> {
+        let user_options = [MOTOR_USER_OPTIONS, MODBUS_USER_OPTIONS, PWM];
+        (self.tuning.then_some(TUNING).into_iter())
+            .chain(user_options.into_iter().filter(|_| self.user_options))
+    }
+
+    pub const ALL: Self = Self {
+        reserve0: 0,
+        tuning: true,
+        user_options: true,
+        motion_config: true,
+        iosh: true,
+        haptic_config: true,
+        reserve1: 0,
+    };
+}
+
+impl From<u16> for SaveGroups {
+    fn from(value: u16) -> Self {
+        Self::from_bytes(value.to_be_bytes())
+    }
+}
+impl From<SaveGroups> for u16 {
+    fn from(val: SaveGroups) -> Self {
+        u16::from_be_bytes(val.into_bytes())
+    }
+}
+
+/// Parameter groups that can be reset to factory defaults, laid out like `CtrlReg4`.
+///
+/// Start from `DefaultGroups::default()` and set the groups wanted.
+#[derive(Bitfields, Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize, Serialize)]
+#[bondrewd(default_endianness = "le", read_from = "lsb0", enforce_bytes = 2)]
+pub struct DefaultGroups {
+    #[bondrewd(bit_length = 1, reserve)]
+    reserve0: u8,
+    pub tuning: bool,
+    pub motor_user_options: bool,
+    pub modbus_user_options: bool,
+    pub kinematic: bool,
+    pub haptic: bool,
+    pub iosh: bool,
+    pub pwm: bool,
+    #[bondrewd(bit_length = 8, reserve)]
+    reserve1: u8,
+}
+
+impl DefaultGroups {
+    /// Register blocks the selected groups cover. The kinematic, haptic and IOSH registers are
+    /// not mapped, so those groups have none.
+    fn registers(&self) -> impl Iterator<Item = RangeInclusive<u16>> {
+        [
+            (self.tuning, TUNING),
+            (self.motor_user_options, MOTOR_USER_OPTIONS),
+            (self.modbus_user_options, MODBUS_USER_OPTIONS),
+            (self.pwm, PWM),
+        ]
+        .into_iter()
+        .filter_map(|(selected, block)| selected.then_some(block))
+    }
+
+    pub const ALL: Self = Self {
+        reserve0: 0,
+        tuning: true,
+        motor_user_options: true,
+        modbus_user_options: true,
+        kinematic: true,
+        haptic: true,
+        iosh: true,
+        pwm: true,
+        reserve1: 0,
+    };
+}
+
+impl From<u16> for DefaultGroups {
+    fn from(value: u16) -> Self {
+        Self::from_bytes(value.to_be_bytes())
+    }
+}
+impl From<DefaultGroups> for u16 {
+    fn from(val: DefaultGroups) -> Self {
+        u16::from_be_bytes(val.into_bytes())
+    }
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    /// Reads every register of `blocks` into the front of `values`, returning how many were
+    /// read.
+    async fn read_blocks(
+        &mut self,
+        blocks: impl Iterator<Item = RangeInclusive<u16>>,
+        values: &mut [u16; MAX_GROUP_REGISTERS],
+    ) -> Result<usize> {
+        let mut len = 0;
+        for block in blocks {
+            let end = len + block.len();
+            self.read_holdings_into(*block.start(), &mut values[len..end])
+                .await?;
+            len = end;
+        }
+        Ok(len)
+    }
+
+    /// Writes `flags` to the control register at `address` and polls it until the motor has
+    /// cleared every requested bit.
+    async fn write_flash_flags<D: DelayNs>(
+        &mut self,
+        address: u16,
+        flags: u16,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        self.write_holding(address, flags).await?;
+
+        let mut elapsed_ms = 0;
+        loop {
+            delay.delay_ms(FLASH_POLL_INTERVAL_MS).await;
+            elapsed_ms += FLASH_POLL_INTERVAL_MS;
+
+            let pending = self.read_holding(address).await? & flags;
+            if pending == 0 {
+                return Ok(());
+            }
+            if elapsed_ms >= timeout_ms {
+                return Err(Error::Timeout {
+                    operation: "Flash write",
+                    timeout_ms,
+                });
+            }
+        }
+    }
+
+    /// Saves `groups` to flash so they survive a power cycle.
+    ///
+    /// The motor offers no way to read its flash back, so the save cannot be verified: the
+    /// flags clearing in `CtrlReg2` is the only sign it was written. The registers of the saved
+    /// groups are re-read around the save, and it fails with [`Error::ReadBack`] if any of them
+    /// changed. A save that wrote nothing still passes this re-read.
+    pub async fn save<D: DelayNs>(
+        &mut self,
+        groups: SaveGroups,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        let mut before = [0; MAX_GROUP_REGISTERS];
+        self.read_blocks(groups.registers(), &mut before).await?;
+        self.write_flash_flags(CtrlReg2::ADDRESS as u16, groups.into(), delay, timeout_ms)
+            .await?;
+
+        let mut after = [0; MAX_GROUP_REGISTERS];
+        self.read_blocks(groups.registers(), &mut after).await?;
+        let addresses = groups.registers().flatten();
+        match addresses
+            .zip(before.iter().zip(&after))
+            .find(|(_, (before, after))| before != after)
+        {
+            Some((address, (&expected, &got))) => Err(Error::ReadBack {
+                address,
+                expected,
+                got,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Restores `groups` to their factory defaults.
+    ///
+    /// Once the motor is done, every mapped register of the restored groups is read back to
+    /// confirm the motor serves them again. The defaults are not known here, so the values
+    /// themselves are not checked.
+    pub async fn restore_defaults<D: DelayNs>(
+        &mut self,
+        groups: DefaultGroups,
+        delay: &mut D,
+        timeout_ms: u32,
+    ) -> Result<()> {
+        self.write_flash_flags(CtrlReg4::ADDRESS as u16, groups.into(), delay, timeout_ms)
+            .await?;
+        self.read_blocks(groups.registers(), &mut [0; MAX_GROUP_REGISTERS])
+            .await?;
+        Ok(())
+    }
+}
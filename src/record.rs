@@ -0,0 +1,283 @@
+//! Transaction logs for post-run analysis.
+//!
+//! A [`Recorder`] given to [`crate::OrcaMotor::with_recorder`] appends one [`Transaction`] per
+//! request the motor sends, as JSON lines or as COBS-framed postcard records, and rotates the
+//! file once it reaches a size limit. [`read_log`] reads either format back.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use serde::{Deserialize, Serialize};
+
+use crate::framing::Framing;
+use crate::pdu_payload::{OrcaHighSpeedRequestPDU, OrcaHighSpeedResponsePDU};
+
+/// One request and its outcome.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct Transaction {
+    /// When the request was sent, from the motor's clock.
+    pub timestamp_us: u64,
+    pub slave: u8,
+    pub exchange: Exchange,
+    /// Time from sending the request to the end of the exchange, from the motor's clock.
+    pub latency_us: u64,
+    /// Why the exchange failed, if it did.
+    pub error: Option<String>,
+    /// Bytes read from the port during the exchange, as they arrived. Kept whether or not
+    /// they made a valid response, so exceptions and corrupted frames can be replayed.
+    /// `None` in logs written before they were recorded.
+    #[serde(default)]
+    pub received: Option<Vec<u8>>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub enum Exchange {
+    HighSpeed {
+        request: OrcaHighSpeedRequestPDU,
+        response: Option<OrcaHighSpeedResponsePDU>,
+    },
+    /// A standard register request and its response as rmodbus frames them for `framing`.
+    /// ASCII frames are kept decoded, LRC included.
+    Standard {
+        framing: Framing,
+        request: Vec<u8>,
+        response: Option<Vec<u8>>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
+pub enum LogFormat {
+    /// One JSON object per line.
+    #[default]
+    Json,
+    /// postcard records, each COBS-encoded and ended by a zero byte.
+    Binary,
+}
+
+/// Appends transactions to a log file.
+pub struct Recorder {
+    path: PathBuf,
+    format: LogFormat,
+    file: File,
+    written: u64,
+    max_bytes: Option<u64>,
+    keep: usize,
+    error: Option<io::Error>,
+    /// Bytes read so far in the exchange being recorded.
+    pub(crate) received: Vec<u8>,
+}
+
+impl Recorder {
+    /// Opens `path` for appending, creating it if needed. The log grows without limit until
+    /// [`Recorder::with_rotation`] is set.
+    pub fn create(path: impl AsRef<Path>, format: LogFormat) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            written: file.metadata()?.len(),
+            path,
+            format,
+            file,
+            max_bytes: None,
+            keep: 0,
+            error: None,
+            received: Vec::new(),
+        })
+    }
+
+    /// Starts a new file before the log would grow past `max_bytes`. The old file becomes
+    /// `<path>.1`, `<path>.1` becomes `<path>.2` and so on, keeping at most `keep` of them.
+    pub fn with_rotation(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self.keep = keep;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Appends `transaction`. A failure is also kept for [`Recorder::take_error`], since the
+    /// motor cannot report it from inside a transaction.
+    pub fn record(&mut self, transaction: &Transaction) -> io::Result<()> {
+        let result = self.append(transaction);
+        if let Err(e) = &result {
+            self.error
+                .get_or_insert_with(|| io::Error::new(e.kind(), e.to_string()));
+        }
+        result
+    }
+
+    /// The first write failure since the last call, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn append(&mut self, transaction: &Transaction) -> io::Result<()> {
+        let bytes = match self.format {
+            LogFormat::Json => {
+                let mut line = serde_json::to_vec(transaction)?;
+                line.push(b'\n');
+                line
+            }
+            LogFormat::Binary => postcard::to_stdvec_cobs(transaction).map_err(io::Error::other)?,
+        };
+        let len = bytes.len() as u64;
+        if self
+            .max_bytes
+            .is_some_and(|max| self.written > 0 && self.written + len > max)
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(&bytes)?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotated(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(std::format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..self.keep).rev() {
+            match fs::rename(self.rotated(index), self.rotated(index + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        if self.keep > 0 {
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// Reads back a log written in `format`.
+pub fn read_log<R: BufRead>(
+    mut reader: R,
+    format: LogFormat,
+) -> impl Iterator<Item = io::Result<Transaction>> {
+    let mut buf = Vec::new();
+    core::iter::from_fn(move || {
+        buf.clear();
+        let delimiter = match format {
+            LogFormat::Json => b'\n',
+            LogFormat::Binary => 0,
+        };
+        match reader.read_until(delimiter, &mut buf) {
+            Ok(0) => None,
+            Ok(_) => Some(match format {
+                LogFormat::Json => serde_json::from_slice(&buf).map_err(io::Error::from),
+                LogFormat::Binary => postcard::from_bytes_cobs(&mut buf).map_err(io::Error::other),
+            }),
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrcaMotor;
+    use crate::pdu_payload::*;
+    use crate::tests::{ScriptedPort, rtu};
+    use futures::executor::block_on;
+    use std::io::BufReader;
+    use std::{format, vec};
+
+    fn log_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("orca-record-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(format!("{}.1", path.display()));
+        path
+    }
+
+    fn read_back(path: &Path, format: LogFormat) -> Vec<Transaction> {
+        let file = BufReader::new(File::open(path).unwrap());
+        read_log(file, format).map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn records_each_exchange_and_rotates() {
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um: 1000 };
+        let request = OrcaHighSpeedRequestADU::new(1, OrcaHighSpeedRequestPDU::Command(command));
+        let mut response = vec![1, 0x64];
+        response.extend_from_slice(&990i32.to_be_bytes());
+        response.extend_from_slice(&12i32.to_be_bytes());
+        response.extend_from_slice(&[0, 3, 30, 0x5D, 0xC0, 0, 0]);
+
+        for format in [LogFormat::Json, LogFormat::Binary] {
+            let path = log_path(&format!("{:?}", format));
+            let port = ScriptedPort::default()
+                .get_holding(1, 317, &[3])
+                .expect(request.to_vec(), rtu(&response));
+            let recorder = Recorder::create(&path, format).unwrap().with_rotation(1, 1);
+            let mut motor = OrcaMotor::new(port).with_recorder(recorder);
+            block_on(motor.read_holding(317)).unwrap();
+            let telemetry = block_on(motor.send_position_high_speed(1000)).unwrap();
+            assert!(motor.recorder_mut().unwrap().take_error().is_none());
+
+            let [read] = read_back(format!("{}.1", path.display()).as_ref(), format)
+                .try_into()
+                .unwrap();
+            assert_eq!(
+                read.exchange,
+                Exchange::Standard {
+                    framing: Framing::Rtu,
+                    request: rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+                    response: Some(rtu(&[1, 0x03, 0x02, 0x00, 0x03])),
+                }
+            );
+            assert_eq!(read.error, None);
+            let [stream] = read_back(&path, format).try_into().unwrap();
+            assert_eq!(
+                stream.exchange,
+                Exchange::HighSpeed {
+                    request: OrcaHighSpeedRequestPDU::Command(command),
+                    response: Some(telemetry),
+                }
+            );
+            assert_eq!(stream.received, Some(rtu(&response)));
+        }
+    }
+
+    #[test]
+    fn failed_exchanges_keep_the_received_bytes() {
+        let read = rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]);
+        let exception = rtu(&[1, 0x83, 0x02]);
+        let truncated = rtu(&[1, 0x03, 0x02, 0x00, 0x03])[..4].to_vec();
+        let path = log_path("failed");
+        let port = ScriptedPort::default()
+            .expect(read.clone(), exception.clone())
+            .expect(read.clone(), truncated.clone());
+        let recorder = Recorder::create(&path, LogFormat::Json).unwrap();
+        let mut motor = OrcaMotor::new(port)
+            .with_response_timeout(crate::tests::NoDelay, 20)
+            .with_recorder(recorder);
+        assert!(block_on(motor.read_holding(317)).is_err());
+        assert!(matches!(
+            block_on(motor.read_holding(317)),
+            Err(crate::Error::Timeout { .. })
+        ));
+
+        let [exception_read, truncated_read] =
+            read_back(&path, LogFormat::Json).try_into().unwrap();
+        assert_eq!(exception_read.received, Some(exception));
+        assert!(exception_read.error.is_some());
+        assert_eq!(truncated_read.received, Some(truncated));
+        assert!(truncated_read.error.is_some());
+    }
+}
@@ -1,5 +1,5 @@
 use bondrewd::BitfieldEnum;
-use embedded_registers::register;
+use embedded_registers::{Register, register};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 
@@ -546,3 +546,158 @@ pub struct AvgPower {
 pub struct CoilTemp {
     coil_temp: u16,
 }
+
+#[register(address = 432, mode = "rw")]
+#[bondrewd(default_endianness = "le", read_from = "lsb0", enforce_bytes = 2)]
+pub struct Error0 {
+    error_0: u16,
+}
+
+#[register(address = 433, mode = "rw")]
+#[bondrewd(default_endianness = "le", read_from = "lsb0", enforce_bytes = 2)]
+pub struct Error1 {
+    error_1: u16,
+}
+
+/// Calls `$callback!` with every register above.
+macro_rules! for_each_register {
+    ($callback:ident) => {
+        $callback![
+            CtrlReg0,
+            CtrlReg1,
+            CtrlReg2,
+            CtrlReg3,
+            CtrlReg4,
+            KinSwTrig,
+            ForceCmdL,
+            ForceCmdH,
+            PosCmdL,
+            PosCmdH,
+            CCPGain,
+            CCIGain,
+            CCFGain,
+            CCMaxDuty,
+            PCPGain,
+            PCIGain,
+            PCDVGain,
+            PCDEGain,
+            PCFSatuL,
+            PCFSatuH,
+            UserMaxTemp,
+            UserMaxForceL,
+            UserMaxForceH,
+            UserMaxPower,
+            SafetyDGain,
+            UserMaxCoilTemp,
+            TempErrHysteresis,
+            PCSoftstartPeriod,
+            PosSign,
+            LogPeriod,
+            UserCommsTimeout,
+            UsrMbBaudLo,
+            UsrMbBaudHi,
+            ForceFilt,
+            PosFilt,
+            UsrMbDelay,
+            UsrMbAddr,
+            ZeroMode,
+            AutoZeroForceN,
+            AutoZeroExitMode,
+            MBRS485Mode,
+            MbForceFilter,
+            MbPosFilter,
+            AutoZeroSpeedMmps,
+            PwmTimeoutMs,
+            PwmTimeConstMs,
+            PwmMinPosL,
+            PwmMinPosH,
+            PwmMaxPosL,
+            PwmMaxPosH,
+            PwmServoType,
+            ModeOfOperation,
+            CalibrationStatus,
+            KinematicStatus,
+            BoardTemp,
+            VddFinal,
+            ShaftPosUmL,
+            ShaftPosUmH,
+            ShaftSpeedMmpsL,
+            ShaftSpeedMmpsH,
+            ShaftAccelMmpssL,
+            ShaftAccelMmpssH,
+            ForceL,
+            ForceH,
+            Power,
+            HbaCurrent,
+            HbbCurrent,
+            HbcCurrent,
+            HbdCurrent,
+            AvgPower,
+            CoilTemp,
+            Error0,
+            Error1,
+        ]
+    };
+}
+
+/// Builds the name and address table of the registers above.
+macro_rules! register_table {
+    ($($register:ident,)*) => {
+        &[$((stringify!($register), $register::ADDRESS as u16)),*]
+    };
+}
+
+/// Every register above by name, for tools that address registers by name.
+pub const REGISTERS: &[(&str, u16)] = for_each_register!(register_table);
+
+/// Address of the register called `name` in [`REGISTERS`], ignoring case.
+pub fn register_address(name: &str) -> Option<u16> {
+    REGISTERS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, address)| *address)
+}
+
+/// Name of the register at `address` in [`REGISTERS`].
+pub fn register_name(address: u16) -> Option<&'static str> {
+    REGISTERS
+        .iter()
+        .find(|(_, a)| *a == address)
+        .map(|(name, _)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Checks that every register reads back the fields it was packed from, starting from any
+    /// contents a motor could return.
+    macro_rules! assert_round_trips {
+        ($($register:ident,)*) => {
+            |raw: u16| -> core::result::Result<(), TestCaseError> {
+                $(
+                    let mut register = $register::default();
+                    register.data_mut().copy_from_slice(&raw.to_le_bytes());
+                    let fields = register.read_all();
+                    let packed = $register::new(fields.clone());
+                    prop_assert_eq!(packed.read_all(), fields, "{}", stringify!($register));
+                    prop_assert_eq!(
+                        $register::new(packed.read_all()).data,
+                        packed.data,
+                        "{}",
+                        stringify!($register)
+                    );
+                )*
+                Ok(())
+            }
+        };
+    }
+
+    proptest! {
+        #[test]
+        fn registers_round_trip(raw: u16) {
+            for_each_register!(assert_round_trips)(raw)?;
+        }
+    }
+}
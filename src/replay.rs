@@ -0,0 +1,434 @@
+//! Replay of recorded transaction logs.
+//!
+//! [`replay`] sends a log's requests to a motor again, keeping the recorded spacing, and
+//! reports where the motor now answers differently. [`ReplayPort`] works the other way round:
+//! it stands in for the motor and answers application code with the recorded responses, so
+//! a session seen in the field can be stepped through again deterministically.
+
+use std::collections::VecDeque;
+use std::string::{String, ToString};
+use std::vec;
+use std::vec::Vec;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_io_async::ErrorKind;
+use rmodbus::generate_ascii_frame;
+use serde::Serialize;
+
+use crate::crc;
+use crate::decode::{DecodedPdu, Direction, decode_frame};
+use crate::framing::{Framing, MAX_ASCII_ADU_LEN};
+use crate::pdu_payload::*;
+use crate::record::{Exchange, Transaction};
+use crate::{Error, OrcaMotor};
+
+/// How an exchange ended, in a form that compares across framings.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub enum Outcome {
+    HighSpeed(OrcaHighSpeedResponsePDU),
+    Standard(DecodedPdu),
+    /// The error the exchange failed with, as displayed.
+    Failed(String),
+}
+
+impl Outcome {
+    /// The outcome `transaction` recorded.
+    pub fn recorded(transaction: &Transaction) -> Self {
+        if let Some(error) = &transaction.error {
+            return Self::Failed(error.clone());
+        }
+        let outcome = match &transaction.exchange {
+            Exchange::HighSpeed { response, .. } => response.map(Self::HighSpeed),
+            Exchange::Standard {
+                framing, response, ..
+            } => response.as_ref().and_then(|response| {
+                let frame = as_rtu(*framing, response);
+                let decoded = decode_frame(&frame, Direction::Response).ok()?;
+                Some(Self::Standard(decoded.pdu))
+            }),
+        };
+        outcome.unwrap_or_else(|| Self::Failed(Error::Malformed.to_string()))
+    }
+}
+
+/// A replayed exchange that differs from the recording. `index` counts transactions from the
+/// start of the log.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Divergence<T> {
+    pub index: usize,
+    pub recorded: T,
+    pub replayed: T,
+}
+
+/// A standard ADU recorded with `framing` as an RTU frame, which `decode` can read.
+fn as_rtu(framing: Framing, adu: &[u8]) -> Vec<u8> {
+    let body = match framing {
+        Framing::Rtu | Framing::RtuOverTcp => return adu.to_vec(),
+        // MBAP header
+        Framing::Tcp => adu.get(6..).unwrap_or_default(),
+        // LRC
+        Framing::Ascii => &adu[..adu.len().saturating_sub(1)],
+    };
+    let mut frame = body.to_vec();
+    frame.extend_from_slice(&crc::checksum(body).to_le_bytes());
+    frame
+}
+
+/// Sends `transactions` to `motor` again and returns every exchange whose outcome changed.
+///
+/// Requests are spaced as recorded, less the time the previous exchange took on the motor's
+/// clock; without a clock the full recorded gap is waited. High-speed requests go to the
+/// recorded slave, standard requests to the motor's own. Telemetry from a real motor seldom
+/// repeats bit for bit, so divergences are a report to sift, not a failure. Give the motor a
+/// response timeout (see [`OrcaMotor::with_response_timeout`]) so a request it no longer
+/// answers shows up as a divergence instead of stalling the replay.
+pub async fn replay<T, R>(
+    motor: &mut OrcaMotor<T, R>,
+    transactions: impl IntoIterator<Item = Transaction>,
+    delay: &mut impl DelayNs,
+) -> Vec<Divergence<Outcome>>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    let mut divergences = Vec::new();
+    let mut previous: Option<(u64, u64)> = None;
+    for (index, transaction) in transactions.into_iter().enumerate() {
+        if let Some((recorded_us, sent_us)) = previous {
+            let gap_us = transaction.timestamp_us.saturating_sub(recorded_us);
+            let elapsed_us = motor.now_us().saturating_sub(sent_us);
+            let wait_us = gap_us.saturating_sub(elapsed_us).min(u32::MAX.into());
+            delay.delay_us(wait_us as u32).await;
+        }
+        previous = Some((transaction.timestamp_us, motor.now_us()));
+
+        let replayed = resend(motor, &transaction).await;
+        let recorded = Outcome::recorded(&transaction);
+        if replayed != recorded {
+            divergences.push(Divergence {
+                index,
+                recorded,
+                replayed,
+            });
+        }
+    }
+    divergences
+}
+
+async fn resend<T, R>(motor: &mut OrcaMotor<T, R>, transaction: &Transaction) -> Outcome
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    let result = match &transaction.exchange {
+        Exchange::HighSpeed { request, .. } => {
+            let adu = OrcaHighSpeedRequestADU::new(transaction.slave, *request);
+            motor
+                .send_high_speed_adu(&adu)
+                .await
+                .map(Outcome::HighSpeed)
+        }
+        Exchange::Standard {
+            framing, request, ..
+        } => match decode_frame(&as_rtu(*framing, request), Direction::Request).map(|f| f.pdu) {
+            Ok(DecodedPdu::ReadHoldings { address, count }) => {
+                let mut values = vec![0; usize::from(count)];
+                motor
+                    .read_holdings_into(address, &mut values)
+                    .await
+                    .map(|()| Outcome::Standard(DecodedPdu::ReadHoldingsResponse { values }))
+            }
+            Ok(DecodedPdu::WriteHolding { address, value }) => motor
+                .write_holding(address, value)
+                .await
+                .map(|()| Outcome::Standard(DecodedPdu::WriteHolding { address, value })),
+            Ok(DecodedPdu::WriteHoldings { address, values }) => {
+                let count = values.len() as u16;
+                motor.write_holdings(address, &values).await.map(|()| {
+                    Outcome::Standard(DecodedPdu::WriteHoldingsResponse { address, count })
+                })
+            }
+            Ok(_) => Err(Error::Malformed),
+            Err(e) => Err(e),
+        },
+    };
+    result.unwrap_or_else(|e| Outcome::Failed(e.to_string()))
+}
+
+/// `frame` as a Modbus ASCII line. Recorded frames come from a file, so one too long for a
+/// line is reported rather than trusted.
+fn ascii(frame: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut line = heapless::Vec::<u8, MAX_ASCII_ADU_LEN>::new();
+    generate_ascii_frame(frame, &mut line).map_err(|_| Error::Malformed)?;
+    Ok(line.to_vec())
+}
+
+/// The request and response of `transaction` as they were on the wire.
+fn wire_frames(transaction: &Transaction) -> Result<(Vec<u8>, Option<Vec<u8>>), Error> {
+    let slave = transaction.slave;
+    Ok(match &transaction.exchange {
+        Exchange::HighSpeed { request, response } => (
+            OrcaHighSpeedRequestADU::new(slave, *request).to_vec(),
+            response.map(|pdu| OrcaHighSpeedResponseADU::new(slave, pdu).to_vec()),
+        ),
+        Exchange::Standard {
+            framing: Framing::Ascii,
+            request,
+            response,
+        } => (ascii(request)?, response.as_deref().map(ascii).transpose()?),
+        Exchange::Standard {
+            request, response, ..
+        } => (request.clone(), response.clone()),
+    })
+}
+
+/// A port that plays a recorded motor back to application code.
+///
+/// Each request written consumes the next transaction and queues the bytes the motor sent
+/// back, exceptions and corrupted frames included. A request that differs from the recorded
+/// one is noted in [`ReplayPort::divergences`] and still gets the recorded bytes, so the
+/// session unfolds as it did in the field. Logs written before received bytes were recorded
+/// replay the decoded response instead.
+///
+/// Once the queued bytes run out, reads fail with [`ErrorKind::TimedOut`] as a port with a
+/// read timeout does, so an exchange that timed out in the field fails again at once instead
+/// of waiting forever. Writes after the end of the log fail with [`ErrorKind::BrokenPipe`],
+/// and writes whose recorded frames cannot be put back on the wire with
+/// [`ErrorKind::InvalidData`].
+pub struct ReplayPort {
+    transactions: VecDeque<Transaction>,
+    index: usize,
+    pending: VecDeque<u8>,
+    divergences: Vec<Divergence<Vec<u8>>>,
+}
+
+impl ReplayPort {
+    pub fn new(transactions: impl IntoIterator<Item = Transaction>) -> Self {
+        Self {
+            transactions: transactions.into_iter().collect(),
+            index: 0,
+            pending: VecDeque::new(),
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Requests that differed from the recording, as recorded and as written.
+    pub fn divergences(&self) -> &[Divergence<Vec<u8>>] {
+        &self.divergences
+    }
+
+    /// Transactions not yet replayed.
+    pub fn remaining(&self) -> usize {
+        self.transactions.len()
+    }
+}
+
+impl embedded_io_async::ErrorType for ReplayPort {
+    type Error = ErrorKind;
+}
+
+impl embedded_io_async::Write for ReplayPort {
+    async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        let transaction = self.transactions.pop_front().ok_or(ErrorKind::BrokenPipe)?;
+        let (request, response) = wire_frames(&transaction).map_err(|_| ErrorKind::InvalidData)?;
+        if buf != request {
+            self.divergences.push(Divergence {
+                index: self.index,
+                recorded: request,
+                replayed: buf.to_vec(),
+            });
+        }
+        self.index += 1;
+        let received = transaction.received.or(response);
+        self.pending.extend(received.into_iter().flatten());
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io_async::Read for ReplayPort {
+    async fn read(&mut self, buf: &mut [u8]) -> core::result::Result<usize, Self::Error> {
+        if self.pending.is_empty() {
+            // the motor sent nothing more in this exchange
+            return Err(ErrorKind::TimedOut);
+        }
+        let n = buf.len().min(self.pending.len());
+        buf.iter_mut()
+            .zip(self.pending.drain(..n))
+            .for_each(|(b, p)| *b = p);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{NoDelay, ScriptedPort, rtu};
+    use futures::executor::block_on;
+
+    fn telemetry(position_um: i32) -> OrcaHighSpeedResponsePDU {
+        OrcaHighSpeedResponsePDU::Command(MotorCommandResponsePDUPayload {
+            position_um,
+            force_mn: 12,
+            power_w: 3,
+            temperature_c: 30,
+            voltage_mv: 24000,
+            error: OrcaErrors::default(),
+        })
+    }
+
+    fn session() -> Vec<Transaction> {
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um: 1000 };
+        vec![
+            Transaction {
+                timestamp_us: 0,
+                slave: 1,
+                exchange: Exchange::Standard {
+                    framing: Framing::Rtu,
+                    request: rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]),
+                    response: Some(rtu(&[1, 0x03, 0x02, 0x00, 0x03])),
+                },
+                latency_us: 0,
+                error: None,
+                received: None,
+            },
+            Transaction {
+                timestamp_us: 2000,
+                slave: 1,
+                exchange: Exchange::HighSpeed {
+                    request: OrcaHighSpeedRequestPDU::Command(command),
+                    response: Some(telemetry(990)),
+                },
+                latency_us: 0,
+                error: None,
+                received: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn replay_reports_changed_responses() {
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um: 1000 };
+        let request = OrcaHighSpeedRequestADU::new(1, OrcaHighSpeedRequestPDU::Command(command));
+        let port = ScriptedPort::default().get_holding(1, 317, &[3]).expect(
+            request.to_vec(),
+            OrcaHighSpeedResponseADU::new(1, telemetry(995)).to_vec(),
+        );
+        let mut motor = OrcaMotor::new(port);
+        let divergences = block_on(replay(&mut motor, session(), &mut NoDelay));
+        assert_eq!(
+            divergences,
+            vec![Divergence {
+                index: 1,
+                recorded: Outcome::HighSpeed(telemetry(990)),
+                replayed: Outcome::HighSpeed(telemetry(995)),
+            }]
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn replay_reports_silent_motor() {
+        let port = ScriptedPort::default().expect(rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]), vec![]);
+        let mut motor = OrcaMotor::new(port).with_response_timeout(NoDelay, 20);
+        let mut transactions = session();
+        transactions.truncate(1);
+        let divergences = block_on(replay(&mut motor, transactions, &mut NoDelay));
+        let [divergence] = &divergences[..] else {
+            panic!("expected one divergence");
+        };
+        assert_eq!(
+            divergence.replayed,
+            Outcome::Failed(
+                Error::Timeout {
+                    operation: "Response",
+                    timeout_ms: 20
+                }
+                .to_string()
+            )
+        );
+        assert!(motor.port.is_done());
+    }
+
+    #[test]
+    fn port_answers_with_recorded_responses() {
+        let mut motor = OrcaMotor::new(ReplayPort::new(session()));
+        assert_eq!(block_on(motor.read_holding(317)), Ok(3));
+        assert_eq!(
+            block_on(motor.send_position_high_speed(2000)),
+            Ok(telemetry(990))
+        );
+        let [divergence] = motor.port.divergences() else {
+            panic!("expected one divergence");
+        };
+        assert_eq!(divergence.index, 1);
+        assert_eq!(motor.port.remaining(), 0);
+        assert_eq!(
+            block_on(motor.read_holding(317)),
+            Err(Error::Io(ErrorKind::BrokenPipe))
+        );
+    }
+
+    #[test]
+    fn oversized_ascii_frames_are_malformed() {
+        let transaction = Transaction {
+            timestamp_us: 0,
+            slave: 1,
+            exchange: Exchange::Standard {
+                framing: Framing::Ascii,
+                request: vec![0; MAX_ASCII_ADU_LEN],
+                response: None,
+            },
+            latency_us: 0,
+            error: None,
+            received: None,
+        };
+        assert_eq!(wire_frames(&transaction), Err(Error::Malformed));
+        let mut port = ReplayPort::new([transaction]);
+        assert_eq!(
+            block_on(embedded_io_async::Write::write(&mut port, &[0])),
+            Err(ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn port_replays_failed_exchanges() {
+        let read = rtu(&[1, 0x03, 0x01, 0x3D, 0x00, 0x01]);
+        let failed = |received: Option<Vec<u8>>| Transaction {
+            timestamp_us: 0,
+            slave: 1,
+            exchange: Exchange::Standard {
+                framing: Framing::Rtu,
+                request: read.clone(),
+                response: None,
+            },
+            latency_us: 0,
+            error: Some(String::from("recorded")),
+            received,
+        };
+        let exception = rtu(&[1, 0x83, 0x02]);
+        let mut motor = OrcaMotor::new(ReplayPort::new([
+            failed(Some(exception)),
+            failed(Some(rtu(&[1, 0x03, 0x02, 0x00, 0x03])[..4].to_vec())),
+            failed(None),
+        ]));
+        assert!(matches!(
+            block_on(motor.read_holding(317)),
+            Err(Error::Modbus(_))
+        ));
+        assert_eq!(
+            block_on(motor.read_holding(317)),
+            Err(Error::Io(ErrorKind::TimedOut))
+        );
+        assert_eq!(
+            block_on(motor.read_holding(317)),
+            Err(Error::Io(ErrorKind::TimedOut))
+        );
+        assert!(motor.port.divergences().is_empty());
+    }
+}
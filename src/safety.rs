@@ -0,0 +1,495 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use embedded_registers::Register;
+use serde::{Deserialize, Serialize};
+
+use crate::pdu_payload::*;
+use crate::register_map::*;
+use crate::{Error, NoTimeout, OrcaMotor, Result};
+
+/// Motor-side protection limits, `UserMaxTemp` through `UserCommsTimeout`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
+pub struct OrcaSafetyLimits {
+    pub max_temp_c: u16,
+    pub max_coil_temp_c: u16,
+    pub temp_hysteresis_c: u16,
+    pub max_force_mn: u32,
+    pub max_power_w: u16,
+    pub safety_d_gain: u16,
+    pub comms_timeout_ms: u16,
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    pub async fn set_safety_limits(&mut self, limits: OrcaSafetyLimits) -> Result<()> {
+        self.write_holdings(
+            UserMaxTemp::ADDRESS as u16,
+            &[
+                limits.max_temp_c,
+                limits.max_force_mn as u16,
+                (limits.max_force_mn >> 16) as u16,
+                limits.max_power_w,
+                limits.safety_d_gain,
+            ],
+        )
+        .await?;
+        self.write_holdings(
+            UserMaxCoilTemp::ADDRESS as u16,
+            &[limits.max_coil_temp_c, limits.temp_hysteresis_c],
+        )
+        .await?;
+        self.write_holding(UserCommsTimeout::ADDRESS as u16, limits.comms_timeout_ms)
+            .await
+    }
+
+    pub async fn safety_limits(&mut self) -> Result<OrcaSafetyLimits> {
+        let block: [u16; 5] = self.read_holdings(UserMaxTemp::ADDRESS as u16).await?;
+        let coil: [u16; 2] = self.read_holdings(UserMaxCoilTemp::ADDRESS as u16).await?;
+        Ok(OrcaSafetyLimits {
+            max_temp_c: block[0],
+            max_force_mn: (block[2] as u32) << 16 | block[1] as u32,
+            max_power_w: block[3],
+            safety_d_gain: block[4],
+            max_coil_temp_c: coil[0],
+            temp_hysteresis_c: coil[1],
+            comms_timeout_ms: self.read_holding(UserCommsTimeout::ADDRESS as u16).await?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub enum EnvelopeAction {
+    /// Drop the command and return an error.
+    Reject,
+    /// Send the nearest setpoint inside the envelope instead.
+    Clamp,
+}
+
+/// Host-side limits applied to every setpoint before it reaches the bus.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub struct SoftEnvelope {
+    pub min_position_um: i32,
+    /// Must not lie below `min_position_um`.
+    pub max_position_um: i32,
+    /// Cap on the force magnitude, in either direction.
+    pub max_force_mn: u32,
+    /// Largest change allowed between two consecutive position setpoints.
+    pub max_position_step_um: u32,
+    /// Largest change allowed between two consecutive force setpoints.
+    pub max_force_step_mn: u32,
+    pub action: EnvelopeAction,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub enum EnvelopeViolation {
+    OutsideStroke,
+    ForceOverCap,
+    PositionStepTooLarge,
+    ForceStepTooLarge,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub struct EnvelopeEvent {
+    pub violation: EnvelopeViolation,
+    pub requested: i32,
+    /// Setpoint that was sent instead, or `None` if the command was rejected.
+    pub sent: Option<i32>,
+}
+
+impl SoftEnvelope {
+    /// Fails with [`Error::InvalidEnvelope`] if the stroke is empty.
+    pub fn validate(&self) -> Result<()> {
+        if self.min_position_um > self.max_position_um {
+            return Err(Error::InvalidEnvelope);
+        }
+        Ok(())
+    }
+
+    /// Limits `requested` to `[min, max]` and to `max_step` away from `last`, returning the
+    /// limited value and the first limit that had to be applied.
+    fn limit(
+        requested: i32,
+        (min, max, bound): (i32, i32, EnvelopeViolation),
+        last: Option<i32>,
+        (max_step, step): (u32, EnvelopeViolation),
+    ) -> (i32, Option<EnvelopeViolation>) {
+        // not `clamp`, which panics on an envelope that skipped `validate`
+        let bounded = requested.max(min).min(max);
+        let mut violation = (bounded != requested).then_some(bound);
+        let Some(last) = last else {
+            return (bounded, violation);
+        };
+        let stepped = bounded
+            .max(last.saturating_sub_unsigned(max_step))
+            .min(last.saturating_add_unsigned(max_step));
+        if stepped != bounded {
+            violation = violation.or(Some(step));
+        }
+        (stepped, violation)
+    }
+
+    pub fn limit_position(
+        &self,
+        position_um: i32,
+        last: Option<i32>,
+    ) -> (i32, Option<EnvelopeViolation>) {
+        Self::limit(
+            position_um,
+            (
+                self.min_position_um,
+                self.max_position_um,
+                EnvelopeViolation::OutsideStroke,
+            ),
+            last,
+            (
+                self.max_position_step_um,
+                EnvelopeViolation::PositionStepTooLarge,
+            ),
+        )
+    }
+
+    pub fn limit_force(
+        &self,
+        force_mn: i32,
+        last: Option<i32>,
+    ) -> (i32, Option<EnvelopeViolation>) {
+        let cap = i32::try_from(self.max_force_mn).unwrap_or(i32::MAX);
+        Self::limit(
+            force_mn,
+            (-cap, cap, EnvelopeViolation::ForceOverCap),
+            last,
+            (self.max_force_step_mn, EnvelopeViolation::ForceStepTooLarge),
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub type EnvelopeEventHandler = Box<dyn FnMut(&EnvelopeEvent) + Send>;
+
+#[cfg(not(feature = "alloc"))]
+pub type EnvelopeEventHandler = fn(&EnvelopeEvent);
+
+/// `OrcaMotor` wrapper that checks every streamed setpoint against a [`SoftEnvelope`].
+///
+/// The first setpoint of each kind has no predecessor, so it is only checked against the
+/// absolute limits.
+pub struct SafeMotor<T, R = NoTimeout> {
+    motor: OrcaMotor<T, R>,
+    envelope: SoftEnvelope,
+    last_position_um: Option<i32>,
+    last_force_mn: Option<i32>,
+    on_event: Option<EnvelopeEventHandler>,
+}
+
+impl<T, R> SafeMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    /// Fails with [`Error::InvalidEnvelope`] if `envelope` does not pass
+    /// [`SoftEnvelope::validate`].
+    pub fn new(motor: OrcaMotor<T, R>, envelope: SoftEnvelope) -> Result<Self> {
+        envelope.validate()?;
+        Ok(Self {
+            motor,
+            envelope,
+            last_position_um: None,
+            last_force_mn: None,
+            on_event: None,
+        })
+    }
+
+    /// Calls `on_event` every time a setpoint is rejected or clamped.
+    #[cfg(feature = "alloc")]
+    pub fn with_event_handler(
+        mut self,
+        on_event: impl FnMut(&EnvelopeEvent) + Send + 'static,
+    ) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    /// Calls `on_event` every time a setpoint is rejected or clamped.
+    #[cfg(not(feature = "alloc"))]
+    pub fn with_event_handler(mut self, on_event: EnvelopeEventHandler) -> Self {
+        self.on_event = Some(on_event);
+        self
+    }
+
+    pub fn motor(&self) -> &OrcaMotor<T, R> {
+        &self.motor
+    }
+
+    pub fn into_inner(self) -> OrcaMotor<T, R> {
+        self.motor
+    }
+
+    pub fn envelope(&self) -> &SoftEnvelope {
+        &self.envelope
+    }
+
+    /// Replaces the envelope, keeping the old one if `envelope` does not pass
+    /// [`SoftEnvelope::validate`].
+    pub fn set_envelope(&mut self, envelope: SoftEnvelope) -> Result<()> {
+        envelope.validate()?;
+        self.envelope = envelope;
+        Ok(())
+    }
+
+    /// Applies the envelope action to a limited setpoint, returning the value to send.
+    fn enforce(
+        &mut self,
+        requested: i32,
+        (limited, violation): (i32, Option<EnvelopeViolation>),
+    ) -> Result<i32> {
+        let Some(violation) = violation else {
+            return Ok(requested);
+        };
+        let sent = match self.envelope.action {
+            EnvelopeAction::Reject => None,
+            EnvelopeAction::Clamp => Some(limited),
+        };
+        if let Some(on_event) = self.on_event.as_mut() {
+            on_event(&EnvelopeEvent {
+                violation,
+                requested,
+                sent,
+            });
+        }
+        sent.ok_or(Error::Rejected {
+            requested,
+            violation,
+        })
+    }
+
+    /// Checks a streamed command against the envelope and sends it. Commands other than
+    /// position and force streams are passed through unchanged.
+    ///
+    /// Steps are measured from the last setpoint the motor acknowledged.
+    pub async fn send_command(
+        &mut self,
+        command: MotorCommandRequestPDUPayload,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        let command = match command {
+            MotorCommandRequestPDUPayload::PositionControlStream { position_um } => {
+                let limited = self
+                    .envelope
+                    .limit_position(position_um, self.last_position_um);
+                let position_um = self.enforce(position_um, limited)?;
+                MotorCommandRequestPDUPayload::PositionControlStream { position_um }
+            }
+            MotorCommandRequestPDUPayload::ForceControlStream { force_mn } => {
+                let limited = self.envelope.limit_force(force_mn, self.last_force_mn);
+                let force_mn = self.enforce(force_mn, limited)?;
+                MotorCommandRequestPDUPayload::ForceControlStream { force_mn }
+            }
+            other => other,
+        };
+        let response = self
+            .motor
+            .send_high_speed(OrcaHighSpeedRequestPDU::Command(command))
+            .await?;
+        match command {
+            MotorCommandRequestPDUPayload::PositionControlStream { position_um } => {
+                self.last_position_um = Some(position_um)
+            }
+            MotorCommandRequestPDUPayload::ForceControlStream { force_mn } => {
+                self.last_force_mn = Some(force_mn)
+            }
+            _ => {}
+        }
+        Ok(response)
+    }
+
+    pub async fn send_position_high_speed(
+        &mut self,
+        position_um: i32,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_command(MotorCommandRequestPDUPayload::PositionControlStream { position_um })
+            .await
+    }
+
+    pub async fn send_force_high_speed(
+        &mut self,
+        force_mn: i32,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_command(MotorCommandRequestPDUPayload::ForceControlStream { force_mn })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::ScriptedPort;
+    use futures::executor::block_on;
+    use std::vec::Vec;
+
+    const ENVELOPE: SoftEnvelope = SoftEnvelope {
+        min_position_um: 1_000,
+        max_position_um: 50_000,
+        max_force_mn: 20_000,
+        max_position_step_um: 500,
+        max_force_step_mn: 1_000,
+        action: EnvelopeAction::Clamp,
+    };
+
+    #[test]
+    fn position_inside_envelope_is_untouched() {
+        assert_eq!(ENVELOPE.limit_position(10_000, Some(9_800)), (10_000, None));
+        assert_eq!(ENVELOPE.limit_position(10_000, None), (10_000, None));
+    }
+
+    #[test]
+    fn position_is_clamped_to_stroke_then_step() {
+        assert_eq!(
+            ENVELOPE.limit_position(60_000, None),
+            (50_000, Some(EnvelopeViolation::OutsideStroke))
+        );
+        assert_eq!(
+            ENVELOPE.limit_position(60_000, Some(49_000)),
+            (49_500, Some(EnvelopeViolation::OutsideStroke))
+        );
+        assert_eq!(
+            ENVELOPE.limit_position(12_000, Some(10_000)),
+            (10_500, Some(EnvelopeViolation::PositionStepTooLarge))
+        );
+    }
+
+    #[test]
+    fn force_is_capped_both_ways() {
+        assert_eq!(
+            ENVELOPE.limit_force(-30_000, None),
+            (-20_000, Some(EnvelopeViolation::ForceOverCap))
+        );
+        assert_eq!(
+            ENVELOPE.limit_force(5_000, Some(3_000)),
+            (4_000, Some(EnvelopeViolation::ForceStepTooLarge))
+        );
+    }
+
+    #[test]
+    fn unvalidated_envelope_never_panics() {
+        let envelope = SoftEnvelope {
+            min_position_um: 10,
+            max_position_um: -10,
+            max_force_mn: u32::MAX,
+            max_position_step_um: u32::MAX,
+            ..ENVELOPE
+        };
+        envelope.limit_position(0, Some(i32::MIN));
+        assert_eq!(
+            envelope.limit_force(i32::MIN, None),
+            (-i32::MAX, Some(EnvelopeViolation::ForceOverCap))
+        );
+
+        let motor = OrcaMotor::new(ScriptedPort::default());
+        assert!(matches!(
+            SafeMotor::new(motor, envelope),
+            Err(Error::InvalidEnvelope)
+        ));
+        let motor = OrcaMotor::new(ScriptedPort::default());
+        let mut safe = SafeMotor::new(motor, ENVELOPE).unwrap();
+        assert_eq!(safe.set_envelope(envelope), Err(Error::InvalidEnvelope));
+        assert_eq!(safe.envelope(), &ENVELOPE);
+    }
+
+    fn position_request(position_um: i32) -> Vec<u8> {
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um };
+        OrcaHighSpeedRequestPDU::Command(command)
+            .to_frame(1)
+            .to_vec()
+    }
+
+    fn telemetry_response(position_um: i32) -> Vec<u8> {
+        let telemetry = MotorCommandResponsePDUPayload {
+            position_um,
+            force_mn: 0,
+            power_w: 0,
+            temperature_c: 30,
+            voltage_mv: 24_000,
+            error: OrcaErrors::default(),
+        };
+        let adu = OrcaHighSpeedResponseADU::new(1, OrcaHighSpeedResponsePDU::Command(telemetry));
+        let mut buf = [0; MAX_HIGH_SPEED_ADU_LEN];
+        let len = adu.encode_into(&mut buf).unwrap();
+        buf[..len].to_vec()
+    }
+
+    #[test]
+    fn rejected_setpoint_is_never_sent() {
+        let envelope = SoftEnvelope {
+            action: EnvelopeAction::Reject,
+            ..ENVELOPE
+        };
+        let port =
+            ScriptedPort::default().expect(position_request(10_000), telemetry_response(10_000));
+        let mut safe = SafeMotor::new(OrcaMotor::new(port), envelope).unwrap();
+        block_on(safe.send_position_high_speed(10_000)).unwrap();
+        assert_eq!(
+            block_on(safe.send_position_high_speed(60_000)),
+            Err(Error::Rejected {
+                requested: 60_000,
+                violation: EnvelopeViolation::OutsideStroke
+            })
+        );
+        assert!(safe.motor().port.is_done());
+    }
+
+    #[test]
+    fn failed_send_keeps_the_last_setpoint() {
+        let mut corrupted = telemetry_response(10_400);
+        corrupted[3] ^= 1;
+        let port = ScriptedPort::default()
+            .expect(position_request(10_000), telemetry_response(10_000))
+            .expect(position_request(10_400), corrupted)
+            .expect(position_request(10_500), telemetry_response(10_500));
+        let mut safe = SafeMotor::new(OrcaMotor::new(port), ENVELOPE).unwrap();
+        block_on(safe.send_position_high_speed(10_000)).unwrap();
+        assert_eq!(
+            block_on(safe.send_position_high_speed(10_400)),
+            Err(Error::Crc)
+        );
+        // 900 um from the last acknowledged setpoint, not 500 from the failed one
+        block_on(safe.send_position_high_speed(10_900)).unwrap();
+        assert!(safe.motor().port.is_done());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn clamped_setpoints_are_sent_and_reported() {
+        use std::sync::{Arc, Mutex};
+
+        let port = ScriptedPort::default()
+            .expect(position_request(50_000), telemetry_response(50_000))
+            .expect(position_request(49_500), telemetry_response(49_500));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut safe = SafeMotor::new(OrcaMotor::new(port), ENVELOPE)
+            .unwrap()
+            .with_event_handler(move |e| sink.lock().unwrap().push(*e));
+        block_on(safe.send_position_high_speed(60_000)).unwrap();
+        block_on(safe.send_position_high_speed(40_000)).unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                EnvelopeEvent {
+                    violation: EnvelopeViolation::OutsideStroke,
+                    requested: 60_000,
+                    sent: Some(50_000),
+                },
+                EnvelopeEvent {
+                    violation: EnvelopeViolation::PositionStepTooLarge,
+                    requested: 40_000,
+                    sent: Some(49_500),
+                },
+            ]
+        );
+        assert!(safe.motor().port.is_done());
+    }
+}
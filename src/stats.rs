@@ -0,0 +1,291 @@
+//! Running transaction statistics kept by every [`crate::OrcaMotor`].
+//!
+//! Latencies are measured on the motor's clock, so they stay zero until one is set with
+//! `with_clock`. Everything is fixed-size, so no allocator is needed.
+
+use serde::Serialize;
+
+use crate::Error;
+use crate::pdu_payload::OrcaHighSpeedRequestPDU;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+pub enum TransactionKind {
+    /// Standard holding register reads.
+    ReadRegisters,
+    /// Standard single and multiple holding register writes.
+    WriteRegisters,
+    HighSpeedManage,
+    HighSpeedCommand,
+    HighSpeedRead,
+    HighSpeedWrite,
+}
+
+impl TransactionKind {
+    pub const ALL: [Self; 6] = [
+        Self::ReadRegisters,
+        Self::WriteRegisters,
+        Self::HighSpeedManage,
+        Self::HighSpeedCommand,
+        Self::HighSpeedRead,
+        Self::HighSpeedWrite,
+    ];
+
+    pub fn of_high_speed(pdu: &OrcaHighSpeedRequestPDU) -> Self {
+        match pdu {
+            OrcaHighSpeedRequestPDU::Manage(_) => Self::HighSpeedManage,
+            OrcaHighSpeedRequestPDU::Command(_) => Self::HighSpeedCommand,
+            OrcaHighSpeedRequestPDU::Read(_) => Self::HighSpeedRead,
+            OrcaHighSpeedRequestPDU::Write(_) => Self::HighSpeedWrite,
+        }
+    }
+}
+
+/// Failed transactions by cause. Only the motor's own response timeout is counted; a timeout
+/// imposed by dropping the motor's future is never seen by the motor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Serialize)]
+pub struct ErrorCounts {
+    /// The port failed or closed mid-frame.
+    pub io: u32,
+    pub crc: u32,
+    pub malformed: u32,
+    /// Modbus exceptions and frames rmodbus rejected.
+    pub modbus: u32,
+    pub slave_mismatch: u32,
+    /// The response timeout ran out.
+    pub timeout: u32,
+    pub estopped: u32,
+    pub other: u32,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u32 {
+        self.io
+            + self.crc
+            + self.malformed
+            + self.modbus
+            + self.slave_mismatch
+            + self.timeout
+            + self.estopped
+            + self.other
+    }
+
+    fn count(&mut self, error: &Error) {
+        let counter = match error {
+            Error::Io(_) | Error::UnexpectedEof => &mut self.io,
+            Error::Crc => &mut self.crc,
+            Error::Malformed => &mut self.malformed,
+            Error::Modbus(_) => &mut self.modbus,
+            Error::SlaveMismatch { .. } => &mut self.slave_mismatch,
+            Error::Timeout { .. } => &mut self.timeout,
+            Error::EStopped => &mut self.estopped,
+            _ => &mut self.other,
+        };
+        *counter = counter.saturating_add(1);
+    }
+}
+
+/// Bits of each latency kept: 16 buckets per power of two, so a percentile is within 1/16 of
+/// the true value.
+const SUB_BUCKET_BITS: u32 = 4;
+const SUB_BUCKETS: usize = 1 << SUB_BUCKET_BITS;
+/// Latencies from this many microseconds up, about a second, share the top bucket.
+const MAX_TRACKED_US: u64 = 1 << 20;
+const BUCKETS: usize = bucket(MAX_TRACKED_US - 1) + 1;
+
+/// Log-linear bucket of `us`, as in HdrHistogram: exact below `2 * SUB_BUCKETS`, then
+/// `SUB_BUCKETS` equal buckets per power of two.
+const fn bucket(us: u64) -> usize {
+    if us < 2 * SUB_BUCKETS as u64 {
+        return us as usize;
+    }
+    let shift = 63 - us.leading_zeros() - SUB_BUCKET_BITS;
+    shift as usize * SUB_BUCKETS + (us >> shift) as usize
+}
+
+/// Highest latency that falls in bucket `index`.
+const fn bucket_high_us(index: usize) -> u64 {
+    if index < 2 * SUB_BUCKETS {
+        return index as u64;
+    }
+    let shift = index / SUB_BUCKETS - 1;
+    let low = ((SUB_BUCKETS + index % SUB_BUCKETS) as u64) << shift;
+    low + (1 << shift) - 1
+}
+
+/// Latency distribution in microseconds, with exact count, minimum, maximum and mean.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u32; BUCKETS],
+    count: u64,
+    sum_us: u64,
+    min_us: u64,
+    max_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: [0; BUCKETS],
+            count: 0,
+            sum_us: 0,
+            min_us: u64::MAX,
+            max_us: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, us: u64) {
+        let index = bucket(us.min(MAX_TRACKED_US - 1));
+        self.buckets[index] = self.buckets[index].saturating_add(1);
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.min_us = self.min_us.min(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min_us(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min_us)
+    }
+
+    pub fn max_us(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max_us)
+    }
+
+    pub fn mean_us(&self) -> Option<u64> {
+        self.sum_us.checked_div(self.count)
+    }
+
+    /// Latency that `percentile` percent of samples do not exceed, rounded up to its bucket
+    /// and capped at the maximum seen.
+    pub fn percentile_us(&self, percentile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        // ceil without std
+        let exact = percentile / 100.0 * self.count as f64;
+        let rank = exact as u64 + u64::from((exact as u64 as f64) < exact);
+        let rank = rank.clamp(1, self.count);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += u64::from(*count);
+            if seen >= rank {
+                return Some(bucket_high_us(index).min(self.max_us));
+            }
+        }
+        Some(self.max_us)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct TransactionStats {
+    /// Transactions attempted, failed ones included.
+    pub count: u64,
+    pub errors: ErrorCounts,
+    /// Round trips of successful transactions.
+    pub latency: LatencyHistogram,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Statistics for each [`TransactionKind`].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct OrcaStats {
+    kinds: [TransactionStats; TransactionKind::ALL.len()],
+}
+
+impl OrcaStats {
+    pub fn get(&self, kind: TransactionKind) -> &TransactionStats {
+        &self.kinds[kind as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TransactionKind, &TransactionStats)> {
+        TransactionKind::ALL.into_iter().zip(&self.kinds)
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        kind: TransactionKind,
+        latency_us: u64,
+        bytes: (u64, u64),
+        error: Option<&Error>,
+    ) {
+        let stats = &mut self.kinds[kind as usize];
+        stats.count += 1;
+        stats.bytes_sent += bytes.0;
+        stats.bytes_received += bytes.1;
+        match error {
+            Some(error) => stats.errors.count(error),
+            None => stats.latency.record(latency_us),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrcaMotor;
+    use crate::pdu_payload::*;
+    use crate::tests::{ScriptedPort, rtu};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use futures::executor::block_on;
+
+    #[test]
+    fn counts_bytes_errors_and_latency_per_kind() {
+        static NOW_US: AtomicU64 = AtomicU64::new(0);
+        let command = MotorCommandRequestPDUPayload::PositionControlStream { position_um: 1000 };
+        let request = OrcaHighSpeedRequestADU::new(1, OrcaHighSpeedRequestPDU::Command(command));
+        let mut corrupted = rtu(&[
+            1, 0x64, 0, 0, 3, 0xE6, 0, 0, 0, 12, 0, 3, 30, 0x5D, 0xC0, 0, 0,
+        ]);
+        corrupted[2] ^= 1;
+        let port = ScriptedPort::default()
+            .get_holding(1, 317, &[3])
+            .expect(request.to_frame().to_vec(), corrupted);
+        let mut motor =
+            OrcaMotor::new(port).with_clock(|| NOW_US.fetch_add(100, Ordering::Relaxed));
+
+        block_on(motor.read_holding(317)).unwrap();
+        assert_eq!(
+            block_on(motor.send_position_high_speed(1000)),
+            Err(Error::Crc)
+        );
+
+        let read = motor.stats().get(TransactionKind::ReadRegisters);
+        assert_eq!(
+            (read.count, read.bytes_sent, read.bytes_received),
+            (1, 8, 7)
+        );
+        assert_eq!(read.latency.count(), 1);
+        assert!(read.latency.min_us().unwrap() > 0);
+        let command = motor.stats().get(TransactionKind::HighSpeedCommand);
+        assert_eq!((command.count, command.errors.crc), (1, 1));
+        assert_eq!(command.latency.count(), 0);
+
+        motor.reset_stats();
+        assert!(motor.stats().iter().all(|(_, stats)| stats.count == 0));
+    }
+
+    #[test]
+    fn buckets_bound_their_latencies() {
+        for us in (0..MAX_TRACKED_US).step_by(7) {
+            let index = bucket(us);
+            assert!(index < BUCKETS);
+            assert!(us <= bucket_high_us(index));
+            assert!(us * 17 / 16 >= bucket_high_us(index), "{} us", us);
+        }
+
+        let mut latency = LatencyHistogram::default();
+        (1..=1000).for_each(|us| latency.record(us));
+        assert_eq!(latency.min_us(), Some(1));
+        assert_eq!(latency.max_us(), Some(1000));
+        assert_eq!(latency.mean_us(), Some(500));
+        assert_eq!(latency.percentile_us(50.0), Some(511));
+        assert_eq!(latency.percentile_us(99.0), Some(991));
+        assert_eq!(latency.percentile_us(100.0), Some(1000));
+    }
+}
@@ -0,0 +1,100 @@
+use embedded_registers::Register;
+use serde::{Deserialize, Serialize};
+
+use crate::register_map::*;
+use crate::{OrcaMotor, Result, flags_value};
+
+/// Current controller gains, `CCPGain` through `CCMaxDuty`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
+pub struct CurrentGains {
+    pub p_gain: u16,
+    pub i_gain: u16,
+    pub f_gain: u16,
+    pub max_duty: u16,
+}
+
+impl CurrentGains {
+    fn to_registers(self) -> [u16; 4] {
+        [self.p_gain, self.i_gain, self.f_gain, self.max_duty]
+    }
+    fn from_registers(r: [u16; 4]) -> Self {
+        Self {
+            p_gain: r[0],
+            i_gain: r[1],
+            f_gain: r[2],
+            max_duty: r[3],
+        }
+    }
+}
+
+/// Position controller gains, `PCPGain` through `PCFSatuH`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PositionGains {
+    pub p_gain: u16,
+    pub i_gain: u16,
+    pub dv_gain: u16,
+    pub de_gain: u16,
+    pub force_saturation_mn: u32,
+}
+
+impl PositionGains {
+    fn to_registers(self) -> [u16; 6] {
+        [
+            self.p_gain,
+            self.i_gain,
+            self.dv_gain,
+            self.de_gain,
+            self.force_saturation_mn as u16,
+            (self.force_saturation_mn >> 16) as u16,
+        ]
+    }
+    fn from_registers(r: [u16; 6]) -> Self {
+        Self {
+            p_gain: r[0],
+            i_gain: r[1],
+            dv_gain: r[2],
+            de_gain: r[3],
+            force_saturation_mn: (r[5] as u32) << 16 | r[4] as u32,
+        }
+    }
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    async fn apply_gains(&mut self, set: impl FnOnce(&mut CtrlReg1)) -> Result<()> {
+        let mut reg = CtrlReg1::default();
+        set(&mut reg);
+        self.write_holding(CtrlReg1::ADDRESS as u16, flags_value(&reg))
+            .await
+    }
+
+    /// Writes the current controller gains and tells the motor to apply them.
+    pub async fn set_current_gains(&mut self, gains: CurrentGains) -> Result<()> {
+        self.write_holdings(CCPGain::ADDRESS as u16, &gains.to_registers())
+            .await?;
+        self.apply_gains(|r| r.write_current_controller_gain_set_flag(true))
+            .await
+    }
+
+    pub async fn current_gains(&mut self) -> Result<CurrentGains> {
+        let r = self.read_holdings(CCPGain::ADDRESS as u16).await?;
+        Ok(CurrentGains::from_registers(r))
+    }
+
+    /// Writes the position controller gains and tells the motor to apply them.
+    pub async fn set_position_gains(&mut self, gains: PositionGains) -> Result<()> {
+        self.write_holdings(PCPGain::ADDRESS as u16, &gains.to_registers())
+            .await?;
+        self.apply_gains(|r| r.write_position_controller_gain_set_flag(true))
+            .await
+    }
+
+    pub async fn position_gains(&mut self) -> Result<PositionGains> {
+        let r = self.read_holdings(PCPGain::ADDRESS as u16).await?;
+        Ok(PositionGains::from_registers(r))
+    }
+}
@@ -0,0 +1,298 @@
+//! Physical-unit newtypes for setpoints and telemetry.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pdu_payload::*;
+use crate::register_map::OrcaAutoZeroExitMode;
+use crate::register_map::OrcaZeroMode;
+use crate::safety::{EnvelopeAction, OrcaSafetyLimits, SafeMotor, SoftEnvelope};
+use crate::tuning::PositionGains;
+use crate::zeroing::AutoZeroParams;
+use crate::{OrcaMotor, Result};
+
+/// Scales `si` by `per_si` and rounds to the nearest integer, or `None` if the result is not
+/// finite or falls outside `[min, max]`.
+fn scale_checked(si: f64, per_si: f64, min: i64, max: i64) -> Option<i64> {
+    let scaled = si * per_si;
+    let rounded = if scaled >= 0.0 {
+        scaled + 0.5
+    } else {
+        scaled - 0.5
+    };
+    (min as f64..=max as f64)
+        .contains(&rounded)
+        .then_some(rounded as i64)
+}
+
+macro_rules! unit {
+    ($(#[$doc:meta])* $name:ident($raw:ty), $from_si:ident, $to_si:ident, $per_si:expr) => {
+        $(#[$doc])*
+        #[derive(
+            Debug,
+            PartialEq,
+            Eq,
+            PartialOrd,
+            Ord,
+            Hash,
+            Default,
+            Copy,
+            Clone,
+            Deserialize,
+            Serialize,
+        )]
+        pub struct $name(pub $raw);
+
+        impl $name {
+            pub fn $from_si(si: f64) -> Option<Self> {
+                scale_checked(si, $per_si, <$raw>::MIN as i64, <$raw>::MAX as i64)
+                    .map(|raw| Self(raw as $raw))
+            }
+
+            pub fn $to_si(self) -> f64 {
+                self.0 as f64 / $per_si
+            }
+        }
+
+        impl From<$raw> for $name {
+            fn from(raw: $raw) -> Self {
+                Self(raw)
+            }
+        }
+
+        impl From<$name> for $raw {
+            fn from(val: $name) -> Self {
+                val.0
+            }
+        }
+    };
+}
+
+unit!(Micrometers(i32), from_meters, to_meters, 1e6);
+unit!(Millinewtons(i32), from_newtons, to_newtons, 1e3);
+unit!(Watts(u16), from_watts, to_watts, 1.0);
+unit!(Celsius(u8), from_celsius, to_celsius, 1.0);
+unit!(Millivolts(u16), from_volts, to_volts, 1e3);
+unit!(
+    /// Millimetres per second.
+    MmPerSec(u16),
+    from_meters_per_second,
+    to_meters_per_second,
+    1e3
+);
+unit!(Milliseconds(u16), from_seconds, to_seconds, 1e3);
+unit!(Microseconds(u16), from_seconds, to_seconds, 1e6);
+
+impl Millinewtons {
+    /// The force as a magnitude cap, or `None` if it is negative.
+    fn cap(self) -> Option<u32> {
+        u32::try_from(self.0).ok()
+    }
+}
+
+impl MotorCommandResponsePDUPayload {
+    pub fn position(&self) -> Micrometers {
+        Micrometers(self.position_um)
+    }
+    pub fn force(&self) -> Millinewtons {
+        Millinewtons(self.force_mn)
+    }
+    pub fn power(&self) -> Watts {
+        Watts(self.power_w)
+    }
+    pub fn temperature(&self) -> Celsius {
+        Celsius(self.temperature_c)
+    }
+    pub fn voltage(&self) -> Millivolts {
+        Millivolts(self.voltage_mv)
+    }
+}
+
+impl AutoZeroParams {
+    /// Builds the parameters from typed values, rounding `force` to the whole newtons
+    /// `AutoZeroForceN` holds. `None` if `force` is negative or too large for it.
+    pub fn from_units(
+        zero_mode: OrcaZeroMode,
+        force: Millinewtons,
+        speed: MmPerSec,
+        exit_mode: OrcaAutoZeroExitMode,
+    ) -> Option<Self> {
+        Some(Self {
+            zero_mode,
+            force_n: scale_checked(force.to_newtons(), 1.0, 0, u16::MAX as i64)? as u16,
+            speed_mmps: speed.0,
+            exit_mode,
+        })
+    }
+}
+
+impl OrcaSafetyLimits {
+    /// Builds the limits from typed values, or `None` if `max_force` is negative.
+    pub fn from_units(
+        max_temp: Celsius,
+        max_coil_temp: Celsius,
+        temp_hysteresis: Celsius,
+        max_force: Millinewtons,
+        max_power: Watts,
+        safety_d_gain: u16,
+        comms_timeout: Milliseconds,
+    ) -> Option<Self> {
+        Some(Self {
+            max_temp_c: max_temp.0.into(),
+            max_coil_temp_c: max_coil_temp.0.into(),
+            temp_hysteresis_c: temp_hysteresis.0.into(),
+            max_force_mn: max_force.cap()?,
+            max_power_w: max_power.0,
+            safety_d_gain,
+            comms_timeout_ms: comms_timeout.0,
+        })
+    }
+}
+
+impl SoftEnvelope {
+    /// Builds the envelope from typed values, or `None` if a cap or step is negative.
+    pub fn from_units(
+        min_position: Micrometers,
+        max_position: Micrometers,
+        max_force: Millinewtons,
+        max_position_step: Micrometers,
+        max_force_step: Millinewtons,
+        action: EnvelopeAction,
+    ) -> Option<Self> {
+        Some(Self {
+            min_position_um: min_position.0,
+            max_position_um: max_position.0,
+            max_force_mn: max_force.cap()?,
+            max_position_step_um: u32::try_from(max_position_step.0).ok()?,
+            max_force_step_mn: max_force_step.cap()?,
+            action,
+        })
+    }
+}
+
+impl PositionGains {
+    /// The gains with `force` as the controller's force saturation, or `None` if it is
+    /// negative.
+    pub fn with_force_saturation(self, force: Millinewtons) -> Option<Self> {
+        Some(Self {
+            force_saturation_mn: force.cap()?,
+            ..self
+        })
+    }
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    pub async fn send_position(
+        &mut self,
+        position: Micrometers,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_position_high_speed(position.0).await
+    }
+
+    pub async fn send_force(&mut self, force: Millinewtons) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_force_high_speed(force.0).await
+    }
+
+    pub async fn read_position(&mut self) -> Result<Micrometers> {
+        Ok(Micrometers(self.read_position_um().await?))
+    }
+
+    /// [`OrcaMotor::enable_high_speed`] with the motor's response delay as a typed value.
+    pub async fn enable_high_speed_with(
+        &mut self,
+        baud_rate: u32,
+        delay: Microseconds,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.enable_high_speed(baud_rate, delay.0).await
+    }
+}
+
+impl<T, R> SafeMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: embedded_hal_async::delay::DelayNs,
+{
+    pub async fn send_position(
+        &mut self,
+        position: Micrometers,
+    ) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_position_high_speed(position.0).await
+    }
+
+    pub async fn send_force(&mut self, force: Millinewtons) -> Result<OrcaHighSpeedResponsePDU> {
+        self.send_force_high_speed(force.0).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn si_conversions_are_checked() {
+        assert_eq!(Micrometers::from_meters(0.0125), Some(Micrometers(12_500)));
+        assert_eq!(Micrometers::from_meters(-0.0000004), Some(Micrometers(0)));
+        assert_eq!(Millinewtons::from_newtons(-1.5), Some(Millinewtons(-1_500)));
+        assert_eq!(Millivolts::from_volts(24.15), Some(Millivolts(24_150)));
+        assert_eq!(Watts::from_watts(-1.0), None);
+        assert_eq!(Millinewtons::from_newtons(f64::NAN), None);
+        assert_eq!(Micrometers::from_meters(3_000.0), None);
+        assert_eq!(Millinewtons(80_000).to_newtons(), 80.0);
+        assert_eq!(Celsius::from_celsius(300.0), None);
+        assert_eq!(Microseconds::from_seconds(0.0005), Some(Microseconds(500)));
+    }
+
+    #[test]
+    fn typed_constructors_refuse_negative_caps() {
+        let envelope = SoftEnvelope::from_units(
+            Micrometers(1_000),
+            Micrometers(50_000),
+            Millinewtons(20_000),
+            Micrometers(500),
+            Millinewtons(1_000),
+            EnvelopeAction::Clamp,
+        );
+        assert_eq!(envelope.map(|e| e.max_force_step_mn), Some(1_000));
+        assert!(
+            SoftEnvelope::from_units(
+                Micrometers(1_000),
+                Micrometers(50_000),
+                Millinewtons(-1),
+                Micrometers(500),
+                Millinewtons(1_000),
+                EnvelopeAction::Clamp,
+            )
+            .is_none()
+        );
+
+        let limits = OrcaSafetyLimits::from_units(
+            Celsius(80),
+            Celsius(120),
+            Celsius(5),
+            Millinewtons(500_000),
+            Watts(300),
+            10,
+            Milliseconds(100),
+        )
+        .unwrap();
+        assert_eq!(
+            (limits.max_coil_temp_c, limits.max_force_mn),
+            (120, 500_000)
+        );
+
+        let gains = PositionGains::default();
+        assert_eq!(
+            gains.with_force_saturation(Millinewtons(100_000)),
+            Some(PositionGains {
+                force_saturation_mn: 100_000,
+                ..gains
+            })
+        );
+        assert_eq!(gains.with_force_saturation(Millinewtons(-1)), None);
+    }
+}
@@ -0,0 +1,76 @@
+use core::convert::Infallible;
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+use embedded_hal_async::delay::DelayNs;
+use embedded_registers::Register;
+
+use crate::register_map::*;
+use crate::{OrcaMotor, Result};
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    /// Sets `UserCommsTimeout` so the motor flags `communication_timeout` when it goes
+    /// `timeout_ms` without a command.
+    pub async fn arm_comms_watchdog(&mut self, timeout_ms: u16) -> Result<()> {
+        self.write_holding(UserCommsTimeout::ADDRESS as u16, timeout_ms)
+            .await?;
+        self.comms_watchdog_ms = Some(timeout_ms);
+        Ok(())
+    }
+
+    pub fn comms_watchdog_ms(&self) -> Option<u16> {
+        self.comms_watchdog_ms
+    }
+
+    /// Sends a cheap read of `ModeOfOperation` if no command has gone out for the armed
+    /// watchdog timeout minus `margin_ms`. Uses the high-speed read when the stream is enabled,
+    /// so the current setpoint is left alone. Without a clock (see
+    /// [`OrcaMotor::with_clock`]) there is no telling how long the motor has been idle, so a
+    /// keepalive is sent on every call.
+    ///
+    /// Returns whether a keepalive was sent.
+    pub async fn keep_alive(&mut self, margin_ms: u32) -> Result<bool> {
+        let Some(timeout_ms) = self.comms_watchdog_ms else {
+            return Ok(false);
+        };
+        if self.clock.is_some() {
+            let idle_us = self.now_us().saturating_sub(self.last_command_us());
+            let due_us = (timeout_ms as u64).saturating_sub(margin_ms as u64) * 1000;
+            if idle_us < due_us {
+                return Ok(false);
+            }
+        }
+        if self.is_high_speed() {
+            self.send_read_high_speed(ModeOfOperation::ADDRESS as u16, 1)
+                .await?;
+        } else {
+            self.read_mode().await?;
+        }
+        Ok(true)
+    }
+}
+
+/// Keeps the comms watchdog of a shared motor fed, checking every `margin_ms / 2`.
+///
+/// Only returns if a keepalive fails.
+pub async fn keepalive_task<M, T, R, D>(
+    motor: &Mutex<M, OrcaMotor<T, R>>,
+    delay: &mut D,
+    margin_ms: u32,
+) -> Result<Infallible>
+where
+    M: RawMutex,
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+    D: DelayNs,
+{
+    loop {
+        delay.delay_ms((margin_ms / 2).max(1)).await;
+        motor.lock().await.keep_alive(margin_ms).await?;
+    }
+}
@@ -0,0 +1,96 @@
+use embedded_hal_async::delay::DelayNs;
+use embedded_registers::Register;
+use serde::{Deserialize, Serialize};
+
+use crate::pdu_payload::OrcaErrors;
+use crate::register_map::*;
+use crate::{Error, OrcaMotor, Result};
+
+/// Interval between `ModeOfOperation` polls while the motor is auto-zeroing.
+pub const AUTO_ZERO_POLL_INTERVAL_MS: u32 = 50;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub struct AutoZeroParams {
+    pub zero_mode: OrcaZeroMode,
+    pub force_n: u16,
+    pub speed_mmps: u16,
+    pub exit_mode: OrcaAutoZeroExitMode,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub struct AutoZeroProgress {
+    pub elapsed_ms: u32,
+    pub mode: OrcaModeOfOperation,
+    pub position_um: i32,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize, Serialize)]
+pub enum AutoZeroOutcome {
+    /// The motor found its zero and left auto-zeroing for `mode`.
+    Completed { mode: OrcaModeOfOperation },
+    /// The motor left auto-zeroing with `auto_zero_failed` set; `errors` holds every active flag.
+    Failed { errors: OrcaErrors },
+}
+
+impl<T, R> OrcaMotor<T, R>
+where
+    T: embedded_io_async::Read + embedded_io_async::Write + Unpin,
+    <T as embedded_io_async::ErrorType>::Error: Send + Sync + 'static,
+    R: DelayNs,
+{
+    pub async fn read_position_um(&mut self) -> Result<i32> {
+        Ok(self.read_holding_u32(ShaftPosUmL::ADDRESS as u16).await? as i32)
+    }
+
+    /// Writes the auto-zero parameters, enters `AutoZeroingMode` and polls until the motor leaves it.
+    ///
+    /// `on_progress` is called after every poll. If the motor is still zeroing after `timeout_ms`,
+    /// it is put back to sleep and an error is returned.
+    pub async fn auto_zero<D: DelayNs>(
+        &mut self,
+        params: AutoZeroParams,
+        delay: &mut D,
+        timeout_ms: u32,
+        mut on_progress: impl FnMut(AutoZeroProgress),
+    ) -> Result<AutoZeroOutcome> {
+        self.write_holding(ZeroMode::ADDRESS as u16, params.zero_mode as u16)
+            .await?;
+        self.write_holding(AutoZeroForceN::ADDRESS as u16, params.force_n)
+            .await?;
+        self.write_holding(AutoZeroSpeedMmps::ADDRESS as u16, params.speed_mmps)
+            .await?;
+        self.write_holding(AutoZeroExitMode::ADDRESS as u16, params.exit_mode as u16)
+            .await?;
+        self.set_mode(OrcaModeOfOperation::AutoZeroingMode).await?;
+
+        let mut elapsed_ms = 0;
+        loop {
+            delay.delay_ms(AUTO_ZERO_POLL_INTERVAL_MS).await;
+            elapsed_ms += AUTO_ZERO_POLL_INTERVAL_MS;
+
+            let mode = self.read_mode().await?;
+            let position_um = self.read_position_um().await?;
+            on_progress(AutoZeroProgress {
+                elapsed_ms,
+                mode,
+                position_um,
+            });
+
+            if mode != OrcaModeOfOperation::AutoZeroingMode {
+                let errors = self.read_errors().await?;
+                return Ok(if errors.auto_zero_failed {
+                    AutoZeroOutcome::Failed { errors }
+                } else {
+                    AutoZeroOutcome::Completed { mode }
+                });
+            }
+            if elapsed_ms >= timeout_ms {
+                self.set_mode(OrcaModeOfOperation::SleepMode).await?;
+                return Err(Error::Timeout {
+                    operation: "Auto-zero",
+                    timeout_ms,
+                });
+            }
+        }
+    }
+}